@@ -8,14 +8,19 @@ use arff_reader::{
 fn main() {
     let now = Instant::now();
 
-    let train_set = DataSet::import("./test_data/lakesDiscreteFold1.arff").expect("File not found");
-    let test_set = DataSet::import("./test_data/lakesDiscreteFold2.arff").expect("File not found");
-    let target = 10;
+    let train_set =
+        DataSet::import("./test_data/lakesDiscreteFold1.arff").expect("Unable to import data set");
+    let test_set =
+        DataSet::import("./test_data/lakesDiscreteFold2.arff").expect("Unable to import data set");
+    let target = "latitude";
     let mut id3 = ID3::default();
     let training_data = ProcessedData::import(&train_set);
-    let testing_data = ProcessedData::import_test_data(&test_set, training_data.layout.as_slice());
-    id3.train(&training_data, target);
-    id3.test(&testing_data, target);
+    let testing_data = training_data
+        .import_test_set(&test_set)
+        .expect("Test set doesn't match the training set");
+    id3.train(&training_data, target)
+        .expect("Unable to find target");
+    id3.test(&testing_data);
 
     println!("Finished in {:?}", now.elapsed())
 }
@@ -4,11 +4,12 @@ use std::time::Instant;
 fn main() {
     let now = Instant::now();
 
-    let target = 18;
-    let train_set = DataSet::import("./test_data/lakesA1.arff").expect("File not found");
-    let test_set = DataSet::import("./test_data/lakesA2.arff").expect("File not found");
-    let knn = KNN::new(train_set);
-    knn.test(&test_set, target);
+    let target = "ph";
+    let train_set = DataSet::import("./test_data/lakesA1.arff").expect("Unable to import data set");
+    let test_set = DataSet::import("./test_data/lakesA2.arff").expect("Unable to import data set");
+    let knn = KNN::new(train_set, target).expect("Unable to find target");
+    knn.test(&test_set)
+        .expect("Test set doesn't match the training set");
 
     println!("Finished in {:?}", now.elapsed())
 }
@@ -5,9 +5,11 @@ use std::time::Instant;
 fn main() {
     let now = Instant::now();
 
-    let target_index = 3;
-    let train_set = DataSet::import("./test_data/linear_test.arff").expect("File not found");
-    let mut linear_model = LinearModel::new(&train_set, target_index);
+    let target = "W";
+    let train_set =
+        DataSet::import("./test_data/linear_test.arff").expect("Unable to import data set");
+    let mut linear_model =
+        LinearModel::new(&train_set, target).expect("Unable to train linear model");
     linear_model.regress();
 
     println!("Finished in {:?}", now.elapsed())
@@ -4,13 +4,17 @@ use std::time::Instant;
 fn main() {
     let now = Instant::now();
 
-    let target_index = 10;
-    let train_set = DataSet::import("./test_data/lakesDiscreteFold1.arff").expect("File not found");
-    let test_set = DataSet::import("./test_data/lakesDiscreteFold2.arff").expect("File not found");
+    let target = "latitude";
+    let train_set =
+        DataSet::import("./test_data/lakesDiscreteFold1.arff").expect("Unable to import data set");
+    let test_set =
+        DataSet::import("./test_data/lakesDiscreteFold2.arff").expect("Unable to import data set");
     (0..=10).for_each(|k| {
-        let naive_bayes = NaiveBayes::new(&train_set, target_index, k);
+        let naive_bayes = NaiveBayes::new(&train_set, target, k).expect("Unable to find target");
         println!("K: {}", k);
-        naive_bayes.test(&test_set, target_index, 1);
+        naive_bayes
+            .test(&test_set, 1)
+            .expect("Test set doesn't match the training set");
         println!();
     });
 
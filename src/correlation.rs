@@ -0,0 +1,374 @@
+use std::fmt;
+
+use crate::data_set::{write_table, Data, DataSet};
+
+/// Measure of how strongly every row attribute goes with every column attribute.
+/// Entries are NaN when a measure isn't defined, e.g. the Pearson correlation of a constant column
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Matrix {
+    pub row_labels: Vec<String>,
+    pub column_labels: Vec<String>,
+    // values[row][column]
+    pub values: Vec<Vec<f32>>,
+}
+
+impl Matrix {
+    /// Entry for the pair of attributes, None if either isn't in the matrix
+    pub fn get(&self, row: &str, column: &str) -> Option<f32> {
+        let row = self.row_labels.iter().position(|label| label == row)?;
+        let column = self
+            .column_labels
+            .iter()
+            .position(|label| label == column)?;
+        Some(self.values[row][column])
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = std::iter::once(String::new()).chain(self.column_labels.iter().cloned());
+        let mut rows = vec![header.collect()];
+        rows.extend(
+            self.row_labels
+                .iter()
+                .zip(&self.values)
+                .map(|(label, values)| {
+                    std::iter::once(label.clone())
+                        .chain(values.iter().map(|value| format!("{:.3}", value)))
+                        .collect()
+                }),
+        );
+        write_table(f, &rows, 1, "")
+    }
+}
+
+// Columns the measures work with, missing entries are None
+enum Column {
+    Numeric(Vec<Option<f32>>),
+    // Ids along with the number of declared values
+    Nominal(Vec<Option<u32>>, usize),
+}
+
+// Numeric (dates included) and nominal attributes, everything else is left out
+fn columns(data_set: &DataSet) -> Vec<(String, Column)> {
+    data_set
+        .get_attributes()
+        .iter()
+        .filter_map(|attribute| {
+            let column = match (attribute.as_numeric(), attribute.get_data()) {
+                (Some(data), _) => Column::Numeric(data.iter().collect()),
+                (None, Data::Nominal(nominal)) => {
+                    Column::Nominal(nominal.iter().collect(), nominal.size())
+                }
+                _ => return None,
+            };
+            Some((attribute.label.clone(), column))
+        })
+        .collect()
+}
+
+// Every measure is computed pairwise: rows missing either value are skipped for that pair only.
+// Instance weights are ignored
+fn square_matrix<F>(columns: &[(String, Column)], measure: F) -> Matrix
+where
+    F: Fn(&Column, &Column) -> f32,
+{
+    let labels: Vec<_> = columns.iter().map(|(label, _)| label.clone()).collect();
+    Matrix {
+        row_labels: labels.clone(),
+        column_labels: labels,
+        values: columns
+            .iter()
+            .map(|(_, lhs)| columns.iter().map(|(_, rhs)| measure(lhs, rhs)).collect())
+            .collect(),
+    }
+}
+
+fn numeric_columns(data_set: &DataSet) -> Vec<(String, Column)> {
+    columns(data_set)
+        .into_iter()
+        .filter(|(_, column)| matches!(column, Column::Numeric(_)))
+        .collect()
+}
+
+fn nominal_columns(data_set: &DataSet) -> Vec<(String, Column)> {
+    columns(data_set)
+        .into_iter()
+        .filter(|(_, column)| matches!(column, Column::Nominal(..)))
+        .collect()
+}
+
+/// Pearson correlation between every pair of numeric attributes
+pub fn pearson(data_set: &DataSet) -> Matrix {
+    square_matrix(&numeric_columns(data_set), |lhs, rhs| {
+        let (x, y) = numeric_pairs(lhs, rhs);
+        pearson_of(&x, &y)
+    })
+}
+
+/// Spearman rank correlation between every pair of numeric attributes, tied values share their average rank
+pub fn spearman(data_set: &DataSet) -> Matrix {
+    square_matrix(&numeric_columns(data_set), |lhs, rhs| {
+        let (x, y) = numeric_pairs(lhs, rhs);
+        pearson_of(&ranks(&x), &ranks(&y))
+    })
+}
+
+/// Cramér's V between every pair of nominal attributes, 0 for independent and 1 for determined
+pub fn cramers_v(data_set: &DataSet) -> Matrix {
+    square_matrix(&nominal_columns(data_set), |lhs, rhs| {
+        Contingency::new(lhs, rhs).cramers_v()
+    })
+}
+
+/// Mutual information in bits between every pair of nominal attributes
+pub fn mutual_information(data_set: &DataSet) -> Matrix {
+    square_matrix(&nominal_columns(data_set), |lhs, rhs| {
+        Contingency::new(lhs, rhs).mutual_information()
+    })
+}
+
+/// Correlation ratio (η) of every numeric attribute (columns) given every nominal attribute (rows):
+/// how much of the numeric attribute's variance is explained by the nominal attribute's groups
+pub fn correlation_ratio(data_set: &DataSet) -> Matrix {
+    let nominal = nominal_columns(data_set);
+    let numeric = numeric_columns(data_set);
+    Matrix {
+        row_labels: nominal.iter().map(|(label, _)| label.clone()).collect(),
+        column_labels: numeric.iter().map(|(label, _)| label.clone()).collect(),
+        values: nominal
+            .iter()
+            .map(|(_, groups)| {
+                numeric
+                    .iter()
+                    .map(|(_, values)| correlation_ratio_of(groups, values))
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// Every numeric and nominal attribute against every other with the measure that fits the pair:
+/// Pearson for two numeric attributes, Cramér's V for two nominal attributes and
+/// the correlation ratio when there's one of each. Every entry is between 0 and 1 except Pearson's
+pub fn association(data_set: &DataSet) -> Matrix {
+    square_matrix(&columns(data_set), |lhs, rhs| match (lhs, rhs) {
+        (Column::Numeric(_), Column::Numeric(_)) => {
+            let (x, y) = numeric_pairs(lhs, rhs);
+            pearson_of(&x, &y)
+        }
+        (Column::Nominal(..), Column::Nominal(..)) => Contingency::new(lhs, rhs).cramers_v(),
+        (Column::Nominal(..), Column::Numeric(_)) => correlation_ratio_of(lhs, rhs),
+        (Column::Numeric(_), Column::Nominal(..)) => correlation_ratio_of(rhs, lhs),
+    })
+}
+
+// Values of rows where both are known
+fn numeric_pairs(lhs: &Column, rhs: &Column) -> (Vec<f64>, Vec<f64>) {
+    let (Column::Numeric(lhs), Column::Numeric(rhs)) = (lhs, rhs) else {
+        unreachable!("Both columns need to be numeric")
+    };
+    lhs.iter()
+        .zip(rhs)
+        .filter_map(|(x, y)| Some(((*x)? as f64, (*y)? as f64)))
+        .unzip()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn pearson_of(x: &[f64], y: &[f64]) -> f32 {
+    if x.is_empty() {
+        return f32::NAN;
+    }
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let (covariance, variance_x, variance_y) = x.iter().zip(y).fold(
+        (0.0, 0.0, 0.0),
+        |(covariance, variance_x, variance_y), (x, y)| {
+            let (dx, dy) = (x - mean_x, y - mean_y);
+            (
+                covariance + dx * dy,
+                variance_x + dx * dx,
+                variance_y + dy * dy,
+            )
+        },
+    );
+    // Constant columns give 0 / 0 = NaN
+    (covariance / (variance_x * variance_y).sqrt()) as f32
+}
+
+// 1 based ranks, ties get the average of the ranks they cover
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<_> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let end = (start..order.len())
+            .find(|index| values[order[*index]] != values[order[start]])
+            .unwrap_or(order.len());
+        let rank = (start + end + 1) as f64 / 2.0;
+        order[start..end]
+            .iter()
+            .for_each(|index| ranks[*index] = rank);
+        start = end;
+    }
+    ranks
+}
+
+fn correlation_ratio_of(groups: &Column, values: &Column) -> f32 {
+    let (Column::Nominal(groups, size), Column::Numeric(values)) = (groups, values) else {
+        unreachable!("Need a nominal and a numeric column")
+    };
+    let pairs: Vec<_> = groups
+        .iter()
+        .zip(values)
+        .filter_map(|(group, value)| Some(((*group)?, (*value)? as f64)))
+        .collect();
+    if pairs.is_empty() {
+        return f32::NAN;
+    }
+    let overall = pairs.iter().map(|(_, value)| value).sum::<f64>() / pairs.len() as f64;
+    // (count, sum) per group
+    let mut totals = vec![(0.0, 0.0); *size];
+    pairs.iter().for_each(|(group, value)| {
+        totals[*group as usize].0 += 1.0;
+        totals[*group as usize].1 += value;
+    });
+    let between = totals
+        .iter()
+        .filter(|(count, _)| *count > 0.0)
+        .map(|(count, sum)| count * (sum / count - overall).powi(2))
+        .sum::<f64>();
+    let total = pairs
+        .iter()
+        .map(|(_, value)| (value - overall).powi(2))
+        .sum::<f64>();
+    (between / total).sqrt() as f32
+}
+
+// Counts of every pair of values where both are known
+struct Contingency {
+    counts: Vec<Vec<f64>>,
+    n: f64,
+}
+
+impl Contingency {
+    fn new(lhs: &Column, rhs: &Column) -> Self {
+        let (Column::Nominal(lhs, lhs_size), Column::Nominal(rhs, rhs_size)) = (lhs, rhs) else {
+            unreachable!("Both columns need to be nominal")
+        };
+        let mut counts = vec![vec![0.0; *rhs_size]; *lhs_size];
+        let mut n = 0.0;
+        lhs.iter().zip(rhs).for_each(|(x, y)| {
+            if let (Some(x), Some(y)) = (x, y) {
+                counts[*x as usize][*y as usize] += 1.0;
+                n += 1.0;
+            }
+        });
+        Self { counts, n }
+    }
+
+    fn row_totals(&self) -> Vec<f64> {
+        self.counts.iter().map(|row| row.iter().sum()).collect()
+    }
+
+    fn column_totals(&self) -> Vec<f64> {
+        (0..self.counts.first().map_or(0, Vec::len))
+            .map(|column| self.counts.iter().map(|row| row[column]).sum())
+            .collect()
+    }
+
+    fn cramers_v(&self) -> f32 {
+        let (rows, columns) = (self.row_totals(), self.column_totals());
+        let chi_squared = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(x, row_total)| {
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(move |(y, column_total)| (x, y, row_total * column_total / self.n))
+            })
+            .filter(|(_, _, expected)| *expected > 0.0)
+            .map(|(x, y, expected)| (self.counts[x][y] - expected).powi(2) / expected)
+            .sum::<f64>();
+        // Values that never show up don't count towards the degrees of freedom
+        let used = |totals: &[f64]| totals.iter().filter(|total| **total > 0.0).count();
+        let degrees = used(&rows).min(used(&columns)).saturating_sub(1);
+        // NaN when either attribute only takes one value
+        (chi_squared / (self.n * degrees as f64)).sqrt() as f32
+    }
+
+    fn mutual_information(&self) -> f32 {
+        let (rows, columns) = (self.row_totals(), self.column_totals());
+        self.counts
+            .iter()
+            .enumerate()
+            .flat_map(|(x, row)| row.iter().enumerate().map(move |(y, count)| (x, y, count)))
+            .filter(|(_, _, count)| **count > 0.0)
+            .map(|(x, y, count)| {
+                let joint = count / self.n;
+                joint * (joint / ((rows[x] / self.n) * (columns[y] / self.n))).log2()
+            })
+            .sum::<f64>() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = "@relation test
+        @attribute x numeric
+        @attribute y numeric
+        @attribute z numeric
+        @attribute a {p, q}
+        @attribute b {r, s}
+        @data
+        1,2,9,p,r
+        2,4,1,p,r
+        3,6,4,q,s
+        4,8,?,q,s
+        5,100,2,q,s";
+
+    #[test]
+    fn test_numeric_correlation() {
+        let data_set: DataSet = TEST.parse().unwrap();
+        let pearson = pearson(&data_set);
+        assert_eq!(pearson.column_labels, ["x", "y", "z"]);
+        assert!((pearson.get("x", "x").unwrap() - 1.0).abs() < 1e-6);
+        assert!(pearson.get("x", "y").unwrap() < 0.9);
+        assert_eq!(pearson.get("x", "a"), None);
+        // y only goes up as x does so the ranks match exactly
+        let spearman = spearman(&data_set);
+        assert!((spearman.get("x", "y").unwrap() - 1.0).abs() < 1e-6);
+        assert_eq!(spearman.get("y", "x"), spearman.get("x", "y"));
+        // Ranks of z where x is known: 4, 1, 3, 2
+        assert!((spearman.get("x", "z").unwrap() + 0.4).abs() < 1e-6);
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 2.0]), [3.5, 1.0, 3.5, 2.0]);
+    }
+
+    #[test]
+    fn test_nominal_association() {
+        let data_set: DataSet = TEST.parse().unwrap();
+        // a and b always agree
+        let cramers_v = cramers_v(&data_set);
+        assert!((cramers_v.get("a", "b").unwrap() - 1.0).abs() < 1e-6);
+        let information = mutual_information(&data_set);
+        // Entropy of a 2/5 3/5 split
+        assert!((information.get("a", "b").unwrap() - 0.971).abs() < 1e-3);
+
+        // x is 1.5 in group p and 4 in group q
+        let ratio = correlation_ratio(&data_set);
+        assert_eq!(ratio.row_labels, ["a", "b"]);
+        assert!((ratio.get("a", "x").unwrap() - 0.866).abs() < 1e-3);
+
+        let association = association(&data_set);
+        assert_eq!(association.get("x", "a"), association.get("a", "x"));
+        assert_eq!(association.get("a", "b"), cramers_v.get("a", "b"));
+        assert!(association.to_string().contains("1.000"));
+    }
+}
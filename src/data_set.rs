@@ -1,67 +1,172 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs;
+use std::error::Error;
 use std::hash::{Hash, Hasher};
-use utc_dt::time::UTCTimestamp;
+use std::str::FromStr;
+use std::{fmt, io};
+
+use itertools::Itertools;
+
+mod csv;
+mod date;
+mod discretize;
+mod encode;
+mod header;
+mod impute;
+mod instance;
+mod relational;
+mod scale;
+mod split;
+mod stream;
+mod summary;
+mod target;
+mod transform;
+mod writer;
+
+pub use csv::CsvOptions;
+pub use date::{Date, DateFormat};
+pub use discretize::{Binning, Discretizer};
+pub use encode::{Encoder, Encoding};
+pub use header::HeaderError;
+pub use impute::{ImputeError, Imputed, Imputer};
+pub use instance::{Instance, Value};
+pub use relational::Relational;
+pub use scale::{MinMaxScaler, RobustScaler, Scaler, StandardScaler};
+pub use split::Split;
+pub use stream::ArffReader;
+pub(crate) use summary::write_table;
+pub use summary::{AttributeSummary, Bin, DataSetSummary, LabelFrequency, NumericStats, Stats};
+pub use target::{Target, TargetError};
 
 // derive(A) is a macro to derive trait A
 // Derivable traits normally require that every field has trait A
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedDataSet"))]
 pub struct DataSet {
     relation: String,
     // This is the classic example of DoD. I'm keeping data of the same type packed together
     // 1234123412341234 vs 1111222233334444
     attributes: Vec<Attribute>,
+    // Instance weights written as {weight} at the end of a row
+    // Left empty when every instance has the default weight of 1
+    weights: Vec<f32>,
 }
 
 impl DataSet {
+    /// Builds a data set out of columns that are already filled in.
+    /// Panics if the columns have different lengths
+    pub fn new(relation: &str, attributes: Vec<Attribute>) -> Self {
+        assert!(
+            attributes
+                .iter()
+                .map(|attribute| attribute.data.len())
+                .all_equal(),
+            "Every attribute needs the same number of entries"
+        );
+        Self {
+            relation: relation.to_owned(),
+            attributes,
+            weights: Vec::new(),
+        }
+    }
+
     // Self is shorthand for the type you're implementing, in this case Self == DataSet
     // Self is also conscious of generics e.g. Self == Foo<T> if you're implementing
     // Foo over a generic type T
-    pub fn import(file_path: &str) -> Result<Self, String> {
-        let raw_data = fs::read_to_string(file_path).map_err(|_| "File not found".to_string())?; //.expect("Unable to read file");
-
-        let relation_index = raw_data.find("@relation").expect("No @relation tag found");
-        let relation = Parser::new(&raw_data, relation_index + 9)
-            .parse_string()
-            .to_owned();
+    /// Reads the file a line at a time, see ArffReader. Files compressed with gzip work too
+    pub fn import(file_path: &str) -> Result<Self, ArffError> {
+        // ? converts the io::Error into an ArffError through the From impl below
+        ArffReader::open(file_path)?.read_all()
+    }
 
-        let mut data_index = raw_data.find("@data").expect("No @data tag found");
+    /// Same as import but numeric columns only store their non-zero entries.
+    /// Meant for wide, mostly zero data sets (usually written as sparse ARFF)
+    pub fn import_sparse(file_path: &str) -> Result<Self, ArffError> {
+        ArffReader::open(file_path)?.sparse().read_all()
+    }
 
-        let mut attributes: Vec<_> = raw_data[0..data_index]
+    // sparse decides how numeric columns are stored, the file can have sparse rows either way
+    fn parse_arff(raw_data: &str, sparse: bool) -> Result<Self, ArffError> {
+        // Line numbers start at 1 to match what a text editor shows
+        let mut lines = raw_data
             .lines()
-            // Remove prefix whitespace. If only whitespace then 0 out size so it gets filtered out
-            .map(|line| {
-                let start_index = line
-                    .find(|c: char| !c.is_whitespace())
-                    .unwrap_or(line.len());
-                &line[start_index..]
-            })
-            .filter(|line| !line.is_empty() && !line.starts_with('%'))
-            .map(|line| Parser::new(line, 0))
-            .map(|mut parser| (parser.parse_string(), parser))
-            .filter_map(|(first_word, mut parser)| match first_word {
-                "@attribute" => Some(parser.parse_attribute()),
-                _ => None,
-            })
-            .collect();
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('%'));
+
+        let mut data_set = Self::parse_header(&mut lines)?;
+        if sparse {
+            data_set.use_sparse_columns();
+        }
+        for (line_number, line) in lines {
+            data_set.parse_row(line, line_number)?;
+        }
+        Ok(data_set)
+    }
 
-        // Finding where actual data starts
-        data_index = (data_index + 5)
-            + raw_data[(data_index + 5)..]
-                .find(|c: char| !c.is_whitespace())
-                .unwrap_or(raw_data.len() - (data_index + 5));
+    // Reads lines up to and including @data, the DataSet doesn't have any rows yet
+    // Lines are expected to be trimmed with blank lines and comments already left out
+    fn parse_header<'a>(
+        lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    ) -> Result<Self, ArffError> {
+        let mut relation = None;
+        let mut attributes = Vec::new();
+        let mut found_data = false;
+
+        // by_ref() lets relational attributes read their nested declarations
+        while let Some((line_number, line)) = lines.next() {
+            let mut parser = Parser::new(line, line_number);
+            // Keywords are case insensitive, Weka writes them in upper case
+            match parser.parse_string()?.to_ascii_lowercase().as_str() {
+                "@relation" => relation = Some(parser.parse_string()?.into_owned()),
+                "@attribute" => attributes.push(parse_declaration(&mut parser, lines.by_ref())?),
+                "@data" => {
+                    found_data = true;
+                    break;
+                }
+                _ => return Err(parser.error("Expected @relation, @attribute or @data")),
+            }
+        }
+
+        let relation = relation.ok_or(ArffError::MissingRelation)?;
+        if !found_data {
+            return Err(ArffError::MissingData);
+        }
 
-        raw_data[data_index..].lines().for_each(|line| {
-            line.split(',')
-                .enumerate()
-                .for_each(|(index, value)| attributes[index].parse_value(value))
-        });
         Ok(Self {
             attributes,
             relation,
+            weights: Vec::new(),
         })
     }
 
+    fn use_sparse_columns(&mut self) {
+        self.attributes.iter_mut().for_each(|attribute| {
+            if let Data::Numeric(data) | Data::Real(data) | Data::Integer(data) =
+                &mut attribute.data
+            {
+                *data = data.to_sparse();
+            }
+        });
+    }
+
+    // Sparse and dense rows can be mixed in the same file
+    fn parse_row(&mut self, line: &str, line_number: usize) -> Result<(), ArffError> {
+        let weight = match line.starts_with('{') {
+            true => parse_sparse_row(&mut self.attributes, line, line_number)?,
+            false => parse_dense_row(&mut self.attributes, line, line_number)?,
+        };
+        // Weights only get stored once a row has a weight other than 1
+        if weight != 1.0 && self.weights.is_empty() {
+            self.weights = vec![1.0; self.get_data_len().saturating_sub(1)];
+        }
+        if !self.weights.is_empty() {
+            self.weights.push(weight);
+        }
+        Ok(())
+    }
+
     pub fn display(&self) {
         println!("Relation: {}", self.relation);
 
@@ -69,15 +174,17 @@ impl DataSet {
             .iter()
             .for_each(|attribute| match &attribute.data {
                 Data::Nominal(data) => println!("{}: {:?}", attribute.label, &data.fields),
-                Data::Numeric(data) | Data::Real(data) => println!(
+                Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => println!(
                     "{}: [min: {}, max: {}]",
                     attribute.label,
                     data.iter()
+                        .flatten()
                         .min_by(|a, b| a.partial_cmp(b).unwrap())
-                        .unwrap_or(&0.0),
+                        .unwrap_or(0.0),
                     data.iter()
+                        .flatten()
                         .max_by(|a, b| a.partial_cmp(b).unwrap())
-                        .unwrap_or(&0.0)
+                        .unwrap_or(0.0)
                 ),
                 _ => (),
             })
@@ -93,42 +200,414 @@ impl DataSet {
     }
 
     pub fn get_data_len(&self) -> usize {
-        if self.get_attributes().is_empty() {
-            0
-        } else {
-            match self.get_attributes()[0].get_data() {
-                Data::Nominal(nominal) => nominal.get_data().len(),
-                Data::Real(data) | Data::Numeric(data) => data.len(),
-                _ => panic!("Need to implement other types"),
-            }
-        }
+        self.get_attributes()
+            .first()
+            .map_or(0, |attribute| attribute.get_data().len())
     }
 
     pub fn get_attributes(&self) -> &[Attribute] {
         &self.attributes
     }
+
+    pub fn get_relation(&self) -> &str {
+        &self.relation
+    }
+
+    /// Weight of the instance, 1 unless the row ended with {weight}
+    pub fn get_weight(&self, data_index: usize) -> f32 {
+        self.weights.get(data_index).copied().unwrap_or(1.0)
+    }
+
+    /// Whether any instance has a weight other than 1
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    /// Replaces the weight of every instance. Panics if there isn't one weight per instance
+    pub fn set_weights(&mut self, weights: Vec<f32>) {
+        assert_eq!(
+            weights.len(),
+            self.get_data_len(),
+            "Need one weight per instance"
+        );
+        self.weights = compact_weights(weights);
+    }
+
+    // Same attributes without any rows
+    fn empty_like(&self) -> Self {
+        Self {
+            relation: self.relation.clone(),
+            weights: Vec::new(),
+            attributes: self
+                .attributes
+                .iter()
+                .map(|attribute| Attribute {
+                    label: attribute.label.clone(),
+                    data: attribute.data.empty_like(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Weights are only stored when at least one isn't 1
+fn compact_weights(weights: Vec<f32>) -> Vec<f32> {
+    match weights.iter().all(|weight| *weight == 1.0) {
+        true => Vec::new(),
+        false => weights,
+    }
+}
+
+// A DataSet as it's deserialized, before it's checked. Files can be corrupt or edited by hand
+// so every column has to be the same length and every entry has to make sense before it's used
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedDataSet {
+    relation: String,
+    attributes: Vec<Attribute>,
+    weights: Vec<f32>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedDataSet> for DataSet {
+    type Error = String;
+
+    fn try_from(data_set: UncheckedDataSet) -> Result<Self, Self::Error> {
+        let len = data_set
+            .attributes
+            .first()
+            .map_or(0, |attribute| attribute.data.len());
+        for attribute in &data_set.attributes {
+            if attribute.data.len() != len {
+                return Err(format!(
+                    "Attribute '{}' has {} entries but the first attribute has {}",
+                    attribute.label,
+                    attribute.data.len(),
+                    len
+                ));
+            }
+            attribute
+                .data
+                .check()
+                .map_err(|reason| format!("Attribute '{}' {}", attribute.label, reason))?;
+        }
+        if !data_set.weights.is_empty() && data_set.weights.len() != len {
+            return Err(format!(
+                "There are {} weights for {} instances",
+                data_set.weights.len(),
+                len
+            ));
+        }
+        Ok(Self {
+            relation: data_set.relation,
+            attributes: data_set.attributes,
+            weights: data_set.weights,
+        })
+    }
+}
+
+// Lets you write "@relation ...".parse::<DataSet>() for ARFF text that's already in memory
+impl FromStr for DataSet {
+    type Err = ArffError;
+
+    fn from_str(raw_data: &str) -> Result<Self, Self::Err> {
+        Self::parse_arff(raw_data, false)
+    }
+}
+
+// An @attribute line, relational attributes carry on over the lines up to @end <name>
+fn parse_declaration<'a>(
+    parser: &mut Parser,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Attribute, ArffError> {
+    let mut attribute = parser.parse_attribute()?;
+    if let Data::Relational(bags) = &mut attribute.data {
+        loop {
+            let (line_number, line) = lines.next().ok_or_else(|| ArffError::MissingEnd {
+                attribute: attribute.label.clone(),
+            })?;
+            let mut parser = Parser::new(line, line_number);
+            match parser.parse_string()?.to_ascii_lowercase().as_str() {
+                "@attribute" => {
+                    let nested = parse_declaration(&mut parser, lines)?;
+                    bags.header_mut().attributes.push(nested)
+                }
+                "@end" if parser.parse_string()? == attribute.label => break,
+                "@end" => return Err(parser.error("@end doesn't match the relational attribute")),
+                _ => return Err(parser.error("Expected @attribute or @end")),
+            }
+        }
+    }
+    Ok(attribute)
+}
+
+// Row written as comma separated values, one for every attribute
+// Returns the instance weight
+fn parse_dense_row(
+    attributes: &mut [Attribute],
+    line: &str,
+    line_number: usize,
+) -> Result<f32, ArffError> {
+    let mut parser = Parser::row(line, line_number);
+    let values = parser.parse_list(false)?;
+    let weight = parser.parse_weight()?;
+    // Checking up front so a bad row can't leave the columns with different lengths
+    if values.len() != attributes.len() {
+        return Err(ArffError::WrongValueCount {
+            line: line_number,
+            expected: attributes.len(),
+            found: values.len(),
+        });
+    }
+    values
+        .iter()
+        .zip(attributes.iter_mut())
+        .enumerate()
+        .try_for_each(|(column, ((value, quoted), attribute))| {
+            attribute.parse_value(value, *quoted, line_number, column)
+        })?;
+    Ok(weight)
+}
+
+// Row written as {index value, index value, ...}
+// Indices have to be increasing and every attribute that's left out gets its default value
+// Returns the instance weight
+fn parse_sparse_row(
+    attributes: &mut [Attribute],
+    line: &str,
+    line_number: usize,
+) -> Result<f32, ArffError> {
+    let invalid_row = |text: &str| ArffError::InvalidSparseRow {
+        line: line_number,
+        text: text.to_owned(),
+    };
+    let mut parser = Parser::row(line, line_number);
+    if !parser.accept('{') {
+        return Err(invalid_row(line));
+    }
+
+    // Collect everything first so a bad row can't leave the columns with different lengths
+    let mut values = Vec::new();
+    while !parser.accept('}') {
+        if !values.is_empty() && !parser.accept(',') {
+            return Err(invalid_row(line));
+        }
+        let index = parser.parse_string()?;
+        let column = index
+            .parse::<usize>()
+            .ok()
+            .filter(|column| *column < attributes.len())
+            .filter(|column| values.last().is_none_or(|(last, _)| column > last))
+            .ok_or_else(|| invalid_row(&index))?;
+        values.push((column, parser.parse_value()?));
+    }
+    let weight = match parser.accept(',') || parser.at_end() {
+        true => parser.parse_weight()?,
+        false => return Err(invalid_row(line)),
+    };
+
+    let mut values = values.into_iter().peekable();
+    attributes
+        .iter_mut()
+        .enumerate()
+        .try_for_each(|(column, attribute)| match values.peek() {
+            Some((index, _)) if *index == column => {
+                let (_, (value, quoted)) = values.next().unwrap();
+                attribute.parse_value(&value, quoted, line_number, column)
+            }
+            _ => {
+                attribute.push_default();
+                Ok(())
+            }
+        })?;
+    Ok(weight)
+}
+
+/// Everything that can go wrong while importing an ARFF file.
+/// Line numbers start at 1, columns are the index of the attribute in the header
+#[derive(Debug)]
+pub enum ArffError {
+    /// The file couldn't be read
+    Io(io::Error),
+    /// No @relation declaration before @data
+    MissingRelation,
+    /// The file never reaches an @data section
+    MissingData,
+    /// A header line that couldn't be understood
+    InvalidHeader {
+        line: usize,
+        text: String,
+        reason: &'static str,
+    },
+    /// An attribute declared with a type we can't parse
+    UnsupportedType {
+        line: usize,
+        attribute: String,
+        data_type: String,
+    },
+    /// A value that couldn't be parsed as its attribute's type
+    InvalidValue {
+        line: usize,
+        column: usize,
+        attribute: String,
+        value: String,
+    },
+    /// A nominal value that isn't one of the values declared in the header
+    UnknownNominal {
+        line: usize,
+        column: usize,
+        attribute: String,
+        value: String,
+    },
+    /// A sparse data row that isn't {index value, ...} with increasing indices
+    InvalidSparseRow { line: usize, text: String },
+    /// A data row that couldn't be split into values
+    InvalidRow {
+        line: usize,
+        text: String,
+        reason: &'static str,
+    },
+    /// A relational attribute without its @end line
+    MissingEnd { attribute: String },
+    /// A nominal attribute declaring more than Nominal::MAX_SIZE values
+    TooManyNominalValues {
+        line: usize,
+        attribute: String,
+        count: usize,
+    },
+    /// A data row with more or fewer values than there are attributes
+    WrongValueCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A quoted value that never gets its closing quote
+    UnterminatedQuote { line: usize },
+}
+
+impl fmt::Display for ArffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Unable to read file: {}", error),
+            Self::MissingRelation => write!(f, "No @relation tag found"),
+            Self::MissingData => write!(f, "No @data tag found"),
+            Self::MissingEnd { attribute } => {
+                write!(
+                    f,
+                    "No @end tag found for relational attribute '{}'",
+                    attribute
+                )
+            }
+            Self::InvalidHeader { line, text, reason } => {
+                write!(f, "Line {}: {} in '{}'", line, reason, text)
+            }
+            Self::UnsupportedType {
+                line,
+                attribute,
+                data_type,
+            } => write!(
+                f,
+                "Line {}: attribute '{}' has unsupported type '{}'",
+                line, attribute, data_type
+            ),
+            Self::InvalidValue {
+                line,
+                column,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "Line {}, column {}: '{}' is not a valid value for attribute '{}'",
+                line, column, value, attribute
+            ),
+            Self::UnknownNominal {
+                line,
+                column,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "Line {}, column {}: '{}' is not declared for nominal attribute '{}'",
+                line, column, value, attribute
+            ),
+            Self::InvalidSparseRow { line, text } => {
+                write!(f, "Line {}: invalid sparse entry '{}'", line, text)
+            }
+            Self::TooManyNominalValues {
+                line,
+                attribute,
+                count,
+            } => write!(
+                f,
+                "Line {}: nominal attribute '{}' declares {} values, at most {} are supported",
+                line,
+                attribute,
+                count,
+                Nominal::MAX_SIZE
+            ),
+            Self::InvalidRow { line, text, reason } => {
+                write!(f, "Line {}: {} in '{}'", line, reason, text)
+            }
+            Self::UnterminatedQuote { line } => {
+                write!(f, "Line {}: quoted value is never closed", line)
+            }
+            Self::WrongValueCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Line {}: expected {} values but found {}",
+                line, expected, found
+            ),
+        }
+    }
+}
+
+impl Error for ArffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ArffError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataEntry {
     Numeric(f32),
-    Nominal(u8),
+    Nominal(u32),
+    /// Written as ? in ARFF files
+    Missing,
 }
 
 impl DataEntry {
     pub fn assume_numeric(&self) -> f32 {
         match self {
             Self::Numeric(value) => *value,
-            _ => panic!("Assumed numeric but is nominal!"),
+            Self::Nominal(_) => panic!("Assumed numeric but is nominal!"),
+            Self::Missing => panic!("Assumed numeric but is missing!"),
         }
     }
 
-    pub fn assume_nominal(&self) -> u8 {
+    pub fn assume_nominal(&self) -> u32 {
         match self {
             Self::Nominal(value) => *value,
-            _ => panic!("Assumed nominal but is numeric!"),
+            Self::Numeric(_) => panic!("Assumed nominal but is numeric!"),
+            Self::Missing => panic!("Assumed nominal but is missing!"),
         }
     }
+
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
 }
 
 // Can't derive because f32 isn't EQ. This is just a marker trait so no method to implement
@@ -143,6 +622,7 @@ impl Hash for DataEntry {
         match self {
             Self::Numeric(data) => data.to_ne_bytes().hash(state),
             Self::Nominal(data) => data.hash(state),
+            Self::Missing => std::mem::discriminant(self).hash(state),
         }
     }
 }
@@ -151,87 +631,597 @@ impl Hash for DataEntry {
 // They can take on any of the variants
 // It takes on the size of the largest variant plus a byte to store which variant
 // Sometimes the byte gets optimized away e.g. Option<NonNullPointer> allows None to be 0
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Data {
     // This is the data that's tightly packed together
     // This makes the CPU cache very happy :)
-    Numeric(Vec<f32>),
-    Real(Vec<f32>),
+    Numeric(Numeric),
+    Real(Numeric),
+    // Stored as floats like the other numbers, values just have to be whole
+    Integer(Numeric),
     // Wrap Nominal because it's a big type (72 bytes!)
     Nominal(Box<Nominal>),
     // This makes the CPU cache sad :( will change to IDs in future
-    String(Vec<String>),
-    Date(Vec<Date>),
-    NotImplemented(String),
+    String(Vec<Option<String>>),
+    Date(Box<DateColumn>),
+    // Multi-instance data, every entry is a bag of instances
+    Relational(Box<Relational>),
 }
 
-#[derive(Debug)]
+impl Data {
+    /// Number of entries in the column, missing entries included
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Numeric(data) | Self::Real(data) | Self::Integer(data) => data.len(),
+            Self::Nominal(nominal) => nominal.len(),
+            Self::String(data) => data.len(),
+            Self::Date(dates) => dates.len(),
+            Self::Relational(bags) => bags.len(),
+        }
+    }
+
+    // Same type of column without any entries
+    fn empty_like(&self) -> Self {
+        match self {
+            Self::Numeric(data) => Self::Numeric(data.empty_like()),
+            Self::Real(data) => Self::Real(data.empty_like()),
+            Self::Integer(data) => Self::Integer(data.empty_like()),
+            Self::Nominal(nominal) => Self::Nominal(Box::new(Nominal::new(nominal.fields.clone()))),
+            Self::String(_) => Self::String(Vec::new()),
+            Self::Date(dates) => Self::Date(Box::new(DateColumn::new(dates.format.clone()))),
+            Self::Relational(bags) => Self::Relational(Box::new(bags.empty_like())),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Name of the type as it's declared in an ARFF header
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Numeric(_) => "numeric",
+            Self::Real(_) => "real",
+            Self::Integer(_) => "integer",
+            Self::Nominal(_) => "nominal",
+            Self::String(_) => "string",
+            Self::Date(_) => "date",
+            Self::Relational(_) => "relational",
+        }
+    }
+}
+
+// Checks for deserialized columns, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl Data {
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Self::Numeric(data) | Self::Real(data) | Self::Integer(data) => data.check(),
+            Self::Nominal(nominal) => nominal.check(),
+            Self::String(_) => Ok(()),
+            Self::Date(dates) => dates.missing.check(dates.dates.len()),
+            // Bags were checked as they were deserialized, they just need the right attributes
+            Self::Relational(bags) => bags.iter().flatten().try_for_each(|bag| {
+                bags.get_header()
+                    .check_header(bag)
+                    .map_err(|error| format!("has a bag that doesn't match: {}", error))
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl MissingMask {
+    fn check(&self, len: usize) -> Result<(), String> {
+        // Bits past the last entry would be counted by count()
+        let extra_bits = match self.bits.last() {
+            Some(word) if !len.is_multiple_of(64) => word >> (len % 64) != 0,
+            _ => false,
+        };
+        match self.len == len && self.bits.len() <= len.div_ceil(64) && !extra_bits {
+            true => Ok(()),
+            false => Err("has a missing value mask that doesn't match its entries".to_owned()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Numeric {
+    fn check(&self) -> Result<(), String> {
+        if let Values::Sparse {
+            indices,
+            values,
+            len,
+        } = &self.values
+        {
+            let increasing = indices.windows(2).all(|pair| pair[0] < pair[1]);
+            let in_range = indices.last().is_none_or(|last| (*last as usize) < *len);
+            if indices.len() != values.len() || !increasing || !in_range {
+                return Err("has sparse entries out of order or out of range".to_owned());
+            }
+        }
+        self.missing.check(self.len())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Nominal {
+    fn check(&self) -> Result<(), String> {
+        let map_matches = self.map.len() == self.fields.len()
+            && self
+                .fields
+                .iter()
+                .enumerate()
+                .all(|(id, field)| self.map.get(field) == Some(&(id as u32)));
+        if self.fields.len() > Self::MAX_SIZE || !map_matches {
+            return Err("has values that don't match their ids".to_owned());
+        }
+        // Narrower storage than the values need would cut off ids pushed later
+        if std::mem::discriminant(&self.data) != std::mem::discriminant(&Ids::new(self.size())) {
+            return Err("stores its ids in the wrong type".to_owned());
+        }
+        self.missing.check(self.data.len())?;
+        match self.iter().flatten().all(|id| (id as usize) < self.size()) {
+            true => Ok(()),
+            false => Err(format!("has ids past its {} values", self.size())),
+        }
+    }
+}
+
+/// One bit per entry, set if the entry is missing.
+/// 64 entries per u64 and words are only allocated up to the last missing entry
+/// so columns without missing values cost nothing extra
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct MissingMask {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl MissingMask {
+    fn push(&mut self, missing: bool) {
+        if missing {
+            self.bits.resize(self.len / 64 + 1, 0);
+            // Can't be None since we just resized to include this word
+            *self.bits.last_mut().unwrap() |= 1 << (self.len % 64);
+        }
+        self.len += 1;
+    }
+
+    fn is_missing(&self, index: usize) -> bool {
+        debug_assert!(index < self.len);
+        self.bits
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+/// Column of numbers that can have missing entries.
+/// Missing entries are stored as 0.0 so always check the mask (or use iter/get)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Numeric {
+    values: Values,
+    missing: MissingMask,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Values {
+    Dense(Vec<f32>),
+    // Only the non-zero entries are stored, indices are increasing
+    Sparse {
+        indices: Vec<u32>,
+        values: Vec<f32>,
+        len: usize,
+    },
+}
+
+impl Numeric {
+    pub fn new() -> Self {
+        Self {
+            values: Values::Dense(Vec::new()),
+            missing: MissingMask::default(),
+        }
+    }
+
+    /// Column that only stores its non-zero entries.
+    /// Looking up a single entry is O(log n) but iterating is still O(n)
+    pub fn new_sparse() -> Self {
+        Self {
+            values: Values::Sparse {
+                indices: Vec::new(),
+                values: Vec::new(),
+                len: 0,
+            },
+            missing: MissingMask::default(),
+        }
+    }
+
+    // Stored the same way but without any entries
+    fn empty_like(&self) -> Self {
+        match self.is_sparse() {
+            true => Self::new_sparse(),
+            false => Self::new(),
+        }
+    }
+
+    pub fn push(&mut self, value: f32) {
+        match &mut self.values {
+            Values::Dense(values) => values.push(value),
+            Values::Sparse {
+                indices,
+                values,
+                len,
+            } => {
+                if value != 0.0 {
+                    indices.push(*len as u32);
+                    values.push(value);
+                }
+                *len += 1;
+            }
+        }
+        self.missing.push(false);
+    }
+
+    pub fn push_missing(&mut self) {
+        match &mut self.values {
+            Values::Dense(values) => values.push(0.0),
+            Values::Sparse { len, .. } => *len += 1,
+        }
+        self.missing.push(true);
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.values {
+            Values::Dense(values) => values.len(),
+            Values::Sparse { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.values, Values::Sparse { .. })
+    }
+
+    /// Copy of the column stored sparsely
+    pub fn to_sparse(&self) -> Self {
+        let mut sparse = Self::new_sparse();
+        self.iter().for_each(|value| match value {
+            Some(value) => sparse.push(value),
+            None => sparse.push_missing(),
+        });
+        sparse
+    }
+
+    /// Copy of the column stored densely
+    pub fn to_dense(&self) -> Self {
+        let mut dense = Self::new();
+        self.iter().for_each(|value| match value {
+            Some(value) => dense.push(value),
+            None => dense.push_missing(),
+        });
+        dense
+    }
+
+    /// Returns None if the entry is missing
+    pub fn get(&self, index: usize) -> Option<f32> {
+        if self.missing.is_missing(index) {
+            return None;
+        }
+        match &self.values {
+            Values::Dense(values) => Some(values[index]),
+            Values::Sparse {
+                indices, values, ..
+            } => Some(
+                indices
+                    .binary_search(&(index as u32))
+                    .map_or(0.0, |position| values[position]),
+            ),
+        }
+    }
+
+    /// Iterates over every entry, use .flatten() to skip the missing ones
+    pub fn iter(&self) -> impl Iterator<Item = Option<f32>> + '_ {
+        // Walking the sparse entries alongside the index avoids a binary search per entry
+        let mut next_sparse = 0;
+        (0..self.len()).map(move |index| {
+            let value = match &self.values {
+                Values::Dense(values) => values[index],
+                Values::Sparse {
+                    indices, values, ..
+                } => match indices.get(next_sparse) {
+                    Some(&sparse_index) if sparse_index as usize == index => {
+                        next_sparse += 1;
+                        values[next_sparse - 1]
+                    }
+                    _ => 0.0,
+                },
+            };
+            match self.missing.is_missing(index) {
+                true => None,
+                false => Some(value),
+            }
+        })
+    }
+
+    pub fn is_missing(&self, index: usize) -> bool {
+        self.missing.is_missing(index)
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.missing.count()
+    }
+}
+
+// Columns are equal if their entries are, no matter how they're stored
+impl PartialEq for Numeric {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Default for Numeric {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<f32> for Numeric {
+    fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
+        let mut numeric = Self::new();
+        iter.into_iter().for_each(|value| numeric.push(value));
+        numeric
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nominal {
     // fields[id] -> field value
     fields: Vec<String>,
     // vector of ids (makes computer cache much happier over vector of strings)
-    data: Vec<u8>,
+    // Missing entries are stored as id 0
+    data: Ids,
+    missing: MissingMask,
     // map[field value] -> id
-    map: HashMap<String, u8>,
+    map: HashMap<String, u32>,
 }
 
 impl Nominal {
+    /// Most values a nominal attribute can declare.
+    /// u32::MAX itself is left free since ID3 uses it to mark missing values
+    pub const MAX_SIZE: usize = u32::MAX as usize;
+
     fn new(fields: Vec<String>) -> Self {
-        let data = Vec::new();
+        debug_assert!(fields.len() <= Self::MAX_SIZE);
+        let data = Ids::new(fields.len());
         let map = HashMap::from_iter(
             fields
                 .iter()
                 .enumerate()
-                .map(|(index, field)| (field.into(), index as u8)),
+                .map(|(index, field)| (field.into(), index as u32)),
         );
 
-        Self { fields, data, map }
+        Self {
+            fields,
+            data,
+            missing: MissingMask::default(),
+            map,
+        }
+    }
+
+    // Returns None if value isn't one of the declared fields
+    fn push(&mut self, value: &str) -> Option<()> {
+        let id = self.map.get(value)?;
+        self.data.push(*id);
+        self.missing.push(false);
+        Some(())
     }
 
-    fn push(&mut self, value: &str) {
-        // Could add new field here rather than panicking
-        let id = self.map.get(value).expect("Value does not match any field");
+    fn push_missing(&mut self) {
+        self.data.push(0);
+        self.missing.push(true);
+    }
 
-        self.data.push(*id);
+    // id has to be less than size()
+    fn push_id(&mut self, id: Option<u32>) {
+        debug_assert!(id.is_none_or(|id| (id as usize) < self.size()));
+        self.data.push(id.unwrap_or(0));
+        self.missing.push(id.is_none());
     }
 
     pub fn size(&self) -> usize {
         self.map.len()
     }
 
-    pub fn get(&self, index: usize) -> &str {
-        &self.fields[self.data[index] as usize]
+    /// Number of entries, missing entries included
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the field value of the entry or None if the entry is missing
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.get_id(index)
+            .map(|id| self.fields[id as usize].as_str())
+    }
+
+    /// Returns the id of the entry or None if the entry is missing
+    pub fn get_id(&self, index: usize) -> Option<u32> {
+        match self.missing.is_missing(index) {
+            true => None,
+            false => Some(self.data.get(index)),
+        }
+    }
+
+    /// Iterates over the ids of every entry, use .flatten() to skip the missing ones
+    pub fn iter(&self) -> impl Iterator<Item = Option<u32>> + '_ {
+        (0..self.data.len()).map(|index| self.get_id(index))
+    }
+
+    pub fn is_missing(&self, index: usize) -> bool {
+        self.missing.is_missing(index)
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.missing.count()
     }
 
     pub fn get_fields(&self) -> &[String] {
         &self.fields
     }
 
-    pub fn get_data(&self) -> &[u8] {
-        &self.data
+    /// Raw ids, missing entries show up as 0 here so check is_missing or use iter().
+    /// Only borrows when the attribute has enough values to be stored as u32
+    pub fn get_data(&self) -> Cow<'_, [u32]> {
+        match &self.data {
+            Ids::U32(ids) => Cow::Borrowed(ids),
+            _ => Cow::Owned(
+                (0..self.data.len())
+                    .map(|index| self.data.get(index))
+                    .collect(),
+            ),
+        }
     }
 
-    pub fn get_map(&self) -> &HashMap<String, u8> {
+    pub fn get_map(&self) -> &HashMap<String, u32> {
         &self.map
     }
 }
 
-#[derive(Debug)]
+// Ids are stored in the smallest type that fits every declared value
+// Most attributes have a handful of values so u8 keeps the column as small as it used to be
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Ids {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Ids {
+    fn new(size: usize) -> Self {
+        match size {
+            0..=0x100 => Self::U8(Vec::new()),
+            0x101..=0x1_0000 => Self::U16(Vec::new()),
+            _ => Self::U32(Vec::new()),
+        }
+    }
+
+    // id always fits since it comes from the map built alongside the storage
+    fn push(&mut self, id: u32) {
+        match self {
+            Self::U8(ids) => ids.push(id as u8),
+            Self::U16(ids) => ids.push(id as u16),
+            Self::U32(ids) => ids.push(id),
+        }
+    }
+
+    fn get(&self, index: usize) -> u32 {
+        match self {
+            Self::U8(ids) => ids[index].into(),
+            Self::U16(ids) => ids[index].into(),
+            Self::U32(ids) => ids[index],
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::U8(ids) => ids.len(),
+            Self::U16(ids) => ids.len(),
+            Self::U32(ids) => ids.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Attribute {
     pub label: String,
     pub data: Data,
 }
 
 impl Attribute {
-    fn parse_value(&mut self, raw_data: &str) {
-        let mut parser = Parser::new(raw_data, 0);
+    // line and column are only used to describe the value if it can't be parsed
+    // value has already been unquoted and unescaped by the Parser
+    fn parse_value(
+        &mut self,
+        value: &str,
+        quoted: bool,
+        line: usize,
+        column: usize,
+    ) -> Result<(), ArffError> {
+        let invalid_value = || ArffError::InvalidValue {
+            line,
+            column,
+            attribute: self.label.clone(),
+            value: value.to_owned(),
+        };
+        // An unquoted ? is a missing value for every type. '?' is just a string
+        if !quoted && value == "?" {
+            match &mut self.data {
+                Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => data.push_missing(),
+                Data::Nominal(data) => data.push_missing(),
+                Data::String(data) => data.push(None),
+                Data::Date(dates) => dates.push_missing(),
+                Data::Relational(bags) => bags.push_missing(),
+            }
+            return Ok(());
+        }
+        match &mut self.data {
+            Data::Numeric(data) | Data::Real(data) => {
+                data.push(value.parse().map_err(|_| invalid_value())?)
+            }
+            Data::Integer(data) => data.push(
+                value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|value| value.fract() == 0.0)
+                    .ok_or_else(invalid_value)?,
+            ),
+            Data::Nominal(data) => data.push(value).ok_or_else(|| ArffError::UnknownNominal {
+                line,
+                column,
+                attribute: self.label.clone(),
+                value: value.to_owned(),
+            })?,
+            Data::String(data) => data.push(Some(value.to_owned())),
+            Data::Date(dates) => {
+                let date = dates.format.parse(value).ok_or_else(invalid_value)?;
+                dates.push(date)
+            }
+            Data::Relational(bags) => bags.push(value, line)?,
+        }
+        Ok(())
+    }
+
+    // What a sparse row means when it leaves this attribute out
+    // Zero for numbers, the first value for nominals, an empty string and the epoch
+    fn push_default(&mut self) {
         match &mut self.data {
-            Data::Numeric(data) => data.push(raw_data.parse().unwrap()),
-            Data::Real(data) => data.push(raw_data.parse().unwrap()),
-            Data::Nominal(data) => data.push(parser.parse_string()),
-            Data::String(data) => data.push(parser.parse_string().to_owned()),
-            Data::Date(data) => data.push(parser.parse_date()),
-            Data::NotImplemented(data_type) => panic!("{} hasn't been implemented", data_type),
+            Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => data.push(0.0),
+            Data::Nominal(nominal) => {
+                nominal.data.push(0);
+                nominal.missing.push(false);
+            }
+            Data::String(data) => data.push(Some(String::new())),
+            Data::Date(dates) => dates.push(Date::default()),
+            Data::Relational(bags) => bags.push_empty(),
         }
     }
 
@@ -242,9 +1232,9 @@ impl Attribute {
         }
     }
 
-    pub fn assume_numeric(&self) -> &[f32] {
+    pub fn assume_numeric(&self) -> &Numeric {
         match &self.data {
-            Data::Numeric(data) => data,
+            Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => data,
             _ => panic!("Made wrong assumption"),
         }
     }
@@ -255,17 +1245,103 @@ impl Attribute {
 
     pub fn get_value(&self, index: usize) -> DataEntry {
         match self.get_data() {
-            Data::Numeric(data) | Data::Real(data) => DataEntry::Numeric(data[index]),
-            Data::Nominal(nominal) => DataEntry::Nominal(nominal.data[index]),
+            Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => data
+                .get(index)
+                .map_or(DataEntry::Missing, DataEntry::Numeric),
+            Data::Nominal(nominal) => nominal
+                .get_id(index)
+                .map_or(DataEntry::Missing, DataEntry::Nominal),
+            // Learners see dates as minutes since 2000
+            Data::Date(dates) => dates.get(index).map_or(DataEntry::Missing, |date| {
+                DataEntry::Numeric(date.learner_minutes())
+            }),
             _ => panic!("Need to implement type!"),
         }
     }
+
+    /// Numeric view of numeric, real and date attributes (dates become Date::learner_minutes).
+    /// Cow means numeric columns are borrowed and only dates need converting
+    pub fn as_numeric(&self) -> Option<Cow<'_, Numeric>> {
+        match &self.data {
+            Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => {
+                Some(Cow::Borrowed(data))
+            }
+            Data::Date(dates) => Some(Cow::Owned(dates.to_numeric())),
+            _ => None,
+        }
+    }
+}
+
+/// Column of dates along with the format they're written in
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateColumn {
+    format: DateFormat,
+    // Missing entries are stored as the epoch
+    dates: Vec<Date>,
+    missing: MissingMask,
 }
 
-// QoL wrapper around UTCTimestamp
-// Not sure if nano-second precision is necessary so will consider storing as 1 u64
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub struct Date(UTCTimestamp);
+impl DateColumn {
+    pub fn new(format: DateFormat) -> Self {
+        Self {
+            format,
+            dates: Vec::new(),
+            missing: MissingMask::default(),
+        }
+    }
+
+    pub fn push(&mut self, date: Date) {
+        self.dates.push(date);
+        self.missing.push(false);
+    }
+
+    pub fn push_missing(&mut self) {
+        self.dates.push(Date::default());
+        self.missing.push(true);
+    }
+
+    pub fn len(&self) -> usize {
+        self.dates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Date> {
+        match self.missing.is_missing(index) {
+            true => None,
+            false => Some(self.dates[index]),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Option<Date>> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    pub fn is_missing(&self, index: usize) -> bool {
+        self.missing.is_missing(index)
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.missing.count()
+    }
+
+    pub fn get_format(&self) -> &DateFormat {
+        &self.format
+    }
+
+    /// Date::learner_minutes of each date, which is how learners see dates
+    pub fn to_numeric(&self) -> Numeric {
+        let mut numeric = Numeric::new();
+        self.iter().for_each(|date| match date {
+            Some(date) => numeric.push(date.learner_minutes()),
+            None => numeric.push_missing(),
+        });
+        numeric
+    }
+}
 
 // Generic over a lifetime 'a
 // Parser is given the same lifetime as the source string
@@ -275,93 +1351,511 @@ pub struct Date(UTCTimestamp);
 struct Parser<'a> {
     raw_data: &'a str,
     index: usize,
+    // Only used for error messages
+    line: usize,
+    // Whether this is a data row, decides which error a bad line gives
+    in_data: bool,
 }
 
 impl<'a> Parser<'a> {
     // Note that Self == Parser<'a>
-    fn new(raw_data: &'a str, index: usize) -> Self {
-        Self { raw_data, index }
-    }
-
-    fn parse_date(&mut self) -> Date {
-        // todo!() just panics if reached, this allows the code to compile
-        todo!("Time to implement parse_date()")
-    }
-
-    fn parse_string(&mut self) -> &'a str {
-        let mut start_index = self.index
-            + self.raw_data[self.index..]
-                .find(|c: char| !c.is_whitespace())
-                .expect("End of file reached!");
-
-        let start_char = self
-            .raw_data
-            .get(start_index..=start_index)
-            .expect("Issue reading character");
-        let length = match start_char {
-            "'" => {
-                start_index += 1;
-                1 + self.raw_data[(start_index + 1)..]
-                    .find('\'')
-                    .expect("Missing closing single quote")
+    fn new(raw_data: &'a str, line: usize) -> Self {
+        Self {
+            raw_data,
+            index: 0,
+            line,
+            in_data: false,
+        }
+    }
+
+    fn row(raw_data: &'a str, line: usize) -> Self {
+        Self {
+            in_data: true,
+            ..Self::new(raw_data, line)
+        }
+    }
+
+    fn error(&self, reason: &'static str) -> ArffError {
+        let text = self.raw_data.to_owned();
+        match self.in_data {
+            true => ArffError::InvalidRow {
+                line: self.line,
+                text,
+                reason,
+            },
+            false => ArffError::InvalidHeader {
+                line: self.line,
+                text,
+                reason,
+            },
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.raw_data[self.index..]
+    }
+
+    // Skips whitespace and returns the next character without consuming it
+    // A % outside of quotes comments out the rest of the line so it counts as the end
+    fn peek(&mut self) -> Option<char> {
+        let rest = self.rest();
+        self.index += rest.len() - rest.trim_start().len();
+        self.rest().chars().next().filter(|c| *c != '%')
+    }
+
+    // Consumes the next character if it's the expected one
+    fn accept(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.index += expected.len_utf8();
+        }
+        found
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    // Returns the next word, or everything between the quotes if the word is quoted
+    // Words end at whitespace or any of , { } %
+    fn parse_string(&mut self) -> Result<Cow<'a, str>, ArffError> {
+        match self.peek() {
+            None => Err(self.error("Unexpected end of line")),
+            Some(quote @ ('\'' | '"')) => self.parse_quoted(quote),
+            Some(_) => {
+                let rest = self.rest();
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || ",{}%".contains(c))
+                    .unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(self.error("Expected a word"));
+                }
+                self.index += end;
+                Ok(Cow::Borrowed(&rest[..end]))
             }
-            "\"" => {
-                start_index += 1;
-                1 + self.raw_data[(start_index + 1)..]
-                    .find('"')
-                    .expect("Missing closing double quote")
+        }
+    }
+
+    // Reads from an opening quote to the matching closing quote
+    // A backslash escapes the next character, \n \r and \t are line breaks and tabs
+    // Only allocates when there's an escape to undo
+    fn parse_quoted(&mut self, quote: char) -> Result<Cow<'a, str>, ArffError> {
+        let rest = &self.rest()[quote.len_utf8()..];
+        let mut unescaped: Option<String> = None;
+        let mut chars = rest.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.index += quote.len_utf8() + index + c.len_utf8();
+                    return Ok(unescaped.map_or(Cow::Borrowed(&rest[..index]), Cow::Owned));
+                }
+                '\\' => {
+                    let text = unescaped.get_or_insert_with(|| rest[..index].to_owned());
+                    match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, 'r')) => text.push('\r'),
+                        Some((_, 't')) => text.push('\t'),
+                        Some((_, c)) => text.push(c),
+                        None => break,
+                    }
+                }
+                c => {
+                    if let Some(text) = &mut unescaped {
+                        text.push(c)
+                    }
+                }
             }
-            _ => self.raw_data[(start_index)..]
-                .find(|c: char| c.is_whitespace())
-                .unwrap_or(self.raw_data.len() - start_index),
-        };
+        }
+        Err(ArffError::UnterminatedQuote { line: self.line })
+    }
 
-        let next_white_space = self.raw_data[(start_index + length)..]
-            .find(|c: char| c.is_whitespace())
-            .unwrap_or(self.raw_data.len());
+    // Reads one value out of a comma separated list and whether it was quoted
+    // Unquoted values run up to the next , } or % so they can have spaces in them
+    fn parse_value(&mut self) -> Result<(Cow<'a, str>, bool), ArffError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => Ok((self.parse_quoted(quote)?, true)),
+            _ => {
+                let rest = self.rest();
+                let end = rest.find([',', '}', '%']).unwrap_or(rest.len());
+                self.index += end;
+                Ok((Cow::Borrowed(rest[..end].trim_end()), false))
+            }
+        }
+    }
 
-        self.index = start_index + length + next_white_space;
+    // Comma separated values up to the end of the line, or up to the closing } if braced
+    // An unbraced list also stops at a trailing {weight}, see parse_weight
+    fn parse_list(&mut self, braced: bool) -> Result<Vec<(Cow<'a, str>, bool)>, ArffError> {
+        let mut values = Vec::new();
+        loop {
+            values.push(self.parse_value()?);
+            match self.peek() {
+                Some(',') => {
+                    self.index += 1;
+                    if !braced && self.peek() == Some('{') {
+                        return Ok(values);
+                    }
+                }
+                Some('}') if braced => {
+                    self.index += 1;
+                    return Ok(values);
+                }
+                None if braced => return Err(self.error("Could not find closing }")),
+                None => return Ok(values),
+                _ => return Err(self.error("Expected , between values")),
+            }
+        }
+    }
 
-        &self.raw_data[start_index..(start_index + length)]
+    // The optional {weight} at the end of a data row, 1 if there isn't one
+    fn parse_weight(&mut self) -> Result<f32, ArffError> {
+        if self.at_end() {
+            return Ok(1.0);
+        }
+        if !self.accept('{') {
+            return Err(self.error("Expected {weight} at the end of the row"));
+        }
+        let (weight, _) = self.parse_value()?;
+        let weight = weight
+            .parse::<f32>()
+            .ok()
+            .filter(|weight| weight.is_finite() && *weight >= 0.0)
+            .ok_or_else(|| self.error("Instance weight has to be a non-negative number"))?;
+        match self.accept('}') && self.at_end() {
+            true => Ok(weight),
+            false => Err(self.error("Expected {weight} at the end of the row")),
+        }
     }
 
-    fn parse_attribute(&mut self) -> Attribute {
-        let label = self.parse_string().to_owned();
-        let start_index = self.raw_data[self.index..]
-            .find(|c: char| !c.is_whitespace())
-            .expect("End of file reached!");
-        let start_char = self.raw_data[self.index..]
-            .get(start_index..=start_index)
-            .expect("Error getting first character");
-        let data = if start_char == "{" {
-            self.parse_nominal()
+    fn parse_attribute(&mut self) -> Result<Attribute, ArffError> {
+        let label = self.parse_string()?.into_owned();
+        let data = if self.peek() == Some('{') {
+            self.parse_nominal(&label)?
         } else {
-            let data_type = self.parse_string();
-            match data_type {
-                "numeric" => Data::Numeric(Vec::new()),
+            let data_type = self.parse_string()?;
+            match data_type.to_ascii_lowercase().as_str() {
+                "numeric" => Data::Numeric(Numeric::new()),
+                "integer" => Data::Integer(Numeric::new()),
                 "string" => Data::String(Vec::new()),
-                "date" => Data::Date(Vec::new()),
-                "real" => Data::Real(Vec::new()),
-                _ => Data::NotImplemented(data_type.to_owned()),
+                "date" => Data::Date(Box::new(DateColumn::new(self.parse_date_format()?))),
+                "real" => Data::Real(Numeric::new()),
+                // Nested attributes are filled in by parse_declaration
+                "relational" => Data::Relational(Box::new(Relational::new(&label))),
+                _ => {
+                    return Err(ArffError::UnsupportedType {
+                        line: self.line,
+                        attribute: label,
+                        data_type: data_type.into_owned(),
+                    })
+                }
             }
         };
-        Attribute { label, data }
-    }
-
-    fn parse_nominal(&mut self) -> Data {
-        self.index += self.raw_data[self.index..]
-            .find('{')
-            .expect("Could not find opening {");
-        let length = self.raw_data[self.index..]
-            .find('}')
-            .expect("Could not find closing }")
-            + 1;
-        let fields = self.raw_data[(self.index + 1)..(self.index + length - 1)]
-            .split(',')
-            .map(|word| Parser::new(word, 0).parse_string().to_owned())
+        Ok(Attribute { label, data })
+    }
+
+    // The format is optional and defaults to ISO-8601
+    fn parse_date_format(&mut self) -> Result<DateFormat, ArffError> {
+        if self.at_end() {
+            return Ok(DateFormat::default());
+        }
+        let pattern = self.parse_string()?;
+        DateFormat::new(&pattern).map_err(|reason| self.error(reason))
+    }
+
+    fn parse_nominal(&mut self, label: &str) -> Result<Data, ArffError> {
+        if !self.accept('{') {
+            return Err(self.error("Could not find opening {"));
+        }
+        let fields = self
+            .parse_list(true)?
+            .into_iter()
+            .map(|(field, quoted)| match quoted || !field.is_empty() {
+                true => Ok(field.into_owned()),
+                false => Err(self.error("Empty nominal value")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if fields.len() > Nominal::MAX_SIZE {
+            return Err(ArffError::TooManyNominalValues {
+                line: self.line,
+                attribute: label.to_owned(),
+                count: fields.len(),
+            });
+        }
+
+        Ok(Data::Nominal(Box::new(Nominal::new(fields))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    const HEADER: &str =
+        "@relation test\n@attribute size numeric\n@attribute colour {red, blue}\n@data\n";
+
+    #[test]
+    fn test_import_valid() {
+        let data_set: DataSet = format!("{}1.5,red\n% comment\n\n2,blue\n", HEADER)
+            .parse()
+            .unwrap();
+        assert_eq!(data_set.get_data_len(), 2);
+        assert_eq!(data_set.get_value(0, 1), DataEntry::Numeric(2.0));
+        assert_eq!(data_set.get_value(1, 1), DataEntry::Nominal(1));
+    }
+
+    #[test]
+    fn test_import_missing_values() {
+        let data_set: DataSet = format!("{}?,red\n1, ?\n", HEADER).parse().unwrap();
+        assert_eq!(data_set.get_value(0, 0), DataEntry::Missing);
+        assert_eq!(data_set.get_value(1, 0), DataEntry::Nominal(0));
+        assert_eq!(data_set.get_value(0, 1), DataEntry::Numeric(1.0));
+        assert_eq!(data_set.get_value(1, 1), DataEntry::Missing);
+        assert_eq!(
+            data_set.get_attributes()[0]
+                .assume_numeric()
+                .missing_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_missing_mask_crosses_words() {
+        let mut numeric = Numeric::new();
+        (0..130).for_each(|index| match index % 3 {
+            0 => numeric.push_missing(),
+            _ => numeric.push(index as f32),
+        });
+        assert_eq!(numeric.missing_count(), 44);
+        assert_eq!(numeric.get(129), None);
+        assert_eq!(numeric.get(128), Some(128.0));
+    }
+
+    #[test]
+    fn test_import_dates() {
+        let data_set: DataSet = "@relation dates
+            @attribute default date
+            @attribute custom date \"dd/MM/yyyy HH:mm\"
+            @data
+            2001-04-03T12:12:12,'03/04/2001 12:12'
+            ?,\"01/01/1970 00:01\""
+            .parse()
+            .unwrap();
+        // Minutes since 2000, the 12 seconds are still there
+        assert_eq!(data_set.get_value(0, 0), DataEntry::Numeric(660_252.2));
+        assert_eq!(data_set.get_value(1, 0), DataEntry::Numeric(660_252.0));
+        assert_eq!(data_set.get_value(0, 1), DataEntry::Missing);
+        assert_eq!(data_set.get_value(1, 1), DataEntry::Numeric(-15_778_079.0));
+
+        let result =
+            "@relation dates\n@attribute when date\n@data\n2001-04-03\n".parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidValue { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_import_sparse_rows() {
+        let text = format!("{}{{}}\n{{1 blue}}\n3,red\n{{0 ?, 1 red}}\n", HEADER);
+        let data_set: DataSet = text.parse().unwrap();
+        let entries: Vec<_> = (0..4)
+            .map(|index| (data_set.get_value(0, index), data_set.get_value(1, index)))
             .collect();
-        self.index += length;
+        assert_eq!(
+            entries,
+            vec![
+                (DataEntry::Numeric(0.0), DataEntry::Nominal(0)),
+                (DataEntry::Numeric(0.0), DataEntry::Nominal(1)),
+                (DataEntry::Numeric(3.0), DataEntry::Nominal(0)),
+                (DataEntry::Missing, DataEntry::Nominal(0)),
+            ]
+        );
+
+        let sparse = DataSet::parse_arff(&text, true).unwrap();
+        let numeric = sparse.get_attributes()[0].assume_numeric();
+        assert!(numeric.is_sparse());
+        assert_eq!(
+            numeric.iter().collect::<Vec<_>>(),
+            vec![Some(0.0), Some(0.0), Some(3.0), None]
+        );
+        assert_eq!(numeric.get(2), Some(3.0));
+
+        let result = format!("{}{{1 red, 0 1}}\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidSparseRow { line: 5, .. })
+        ));
+        let result = format!("{}{{2 1}}\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidSparseRow { line: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_import_quoted_values() {
+        let data_set: DataSet = "@relation 'quoting test' % comment
+            @attribute city {'New York, NY', \"St. John's\"}
+            @attribute note string
+            @data
+            'New York, NY', 'it\\'s, \\\"quoted\\\"'  % trailing comment
+            \"St. John's\",  plain words
+            {1 'a, b'}"
+            .parse()
+            .unwrap();
+        let notes = match data_set.get_attributes()[1].get_data() {
+            Data::String(notes) => notes,
+            _ => unreachable!(),
+        };
+        assert_eq!(data_set.get_value(0, 1), DataEntry::Nominal(1));
+        assert_eq!(notes[0].as_deref(), Some("it's, \"quoted\""));
+        assert_eq!(notes[1].as_deref(), Some("plain words"));
+        assert_eq!(notes[2].as_deref(), Some("a, b"));
+
+        let result = format!("{}1.5,'red\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::UnterminatedQuote { line: 5 })
+        ));
+        let result = format!("{}1.5,'red' blue\n", HEADER).parse::<DataSet>();
+        assert!(matches!(result, Err(ArffError::InvalidRow { line: 5, .. })));
+    }
+
+    #[test]
+    fn test_import_upper_case_keywords() {
+        let data_set: DataSet = "@RELATION Test
+            @ATTRIBUTE count INTEGER
+            @Attribute size Numeric
+            @ATTRIBUTE when DATE 'yyyy'
+            @DATA
+            3,1.5,2001"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            data_set.get_attributes()[0].get_data(),
+            Data::Integer(_)
+        ));
+        assert_eq!(data_set.get_value(0, 0), DataEntry::Numeric(3.0));
+        assert_eq!(data_set.get_value(1, 0), DataEntry::Numeric(1.5));
+
+        let result = "@relation test\n@attribute count integer\n@data\n1.5\n".parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidValue { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_import_wide_nominal() {
+        let labels = (0..300).map(|index| format!("zip{}", index)).join(",");
+        let data_set: DataSet = format!(
+            "@relation zips\n@attribute zip {{{}}}\n@data\nzip299\nzip3\n?\n",
+            labels
+        )
+        .parse()
+        .unwrap();
+        let nominal = data_set.get_attributes()[0].assume_nominal();
+        assert!(matches!(nominal.data, Ids::U16(_)));
+        assert_eq!(data_set.get_value(0, 0), DataEntry::Nominal(299));
+        assert_eq!(nominal.get(1), Some("zip3"));
+        assert_eq!(nominal.get_data().as_ref(), [299, 3, 0]);
+        assert!(matches!(Ids::new(256), Ids::U8(_)));
+        assert!(matches!(Ids::new(70_000), Ids::U32(_)));
+    }
+
+    #[test]
+    fn test_import_weights() {
+        let text = format!("{}1.5,red\n2,blue,{{0.5}}\n{{1 blue}},{{3}}\n", HEADER);
+        let data_set: DataSet = text.parse().unwrap();
+        assert!(data_set.is_weighted());
+        let weights: Vec<_> = (0..3).map(|index| data_set.get_weight(index)).collect();
+        assert_eq!(weights, [1.0, 0.5, 3.0]);
+        assert_eq!(data_set.get_value(1, 1), DataEntry::Nominal(1));
+
+        let mut bytes = Vec::new();
+        data_set.write_arff(&mut bytes).unwrap();
+        let written: DataSet = String::from_utf8(bytes).unwrap().parse().unwrap();
+        assert_eq!(written, data_set);
+
+        let data_set: DataSet = format!("{}1.5,red,{{1}}\n", HEADER).parse().unwrap();
+        assert!(!data_set.is_weighted());
+        let result = format!("{}1.5,red,{{-2}}\n", HEADER).parse::<DataSet>();
+        assert!(matches!(result, Err(ArffError::InvalidRow { line: 5, .. })));
+    }
+
+    #[test]
+    fn test_import_invalid_numeric() {
+        let result = format!("{}1.5,red\nbig,blue\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidValue { line: 6, column: 0, ref attribute, ref value })
+                if attribute == "size" && value == "big"
+        ));
+    }
+
+    #[test]
+    fn test_import_unknown_nominal() {
+        let result = format!("{}1.5,green\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::UnknownNominal { line: 5, column: 1, ref value, .. }) if value == "green"
+        ));
+    }
+
+    #[test]
+    fn test_import_wrong_value_count() {
+        let result = format!("{}1.5,red,3\n", HEADER).parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::WrongValueCount {
+                line: 5,
+                expected: 2,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_import_missing_tags() {
+        let result = "@attribute size numeric\n@data\n".parse::<DataSet>();
+        assert!(matches!(result, Err(ArffError::MissingRelation)));
+        let result = "@relation test\n@attribute size numeric\n".parse::<DataSet>();
+        assert!(matches!(result, Err(ArffError::MissingData)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_file_rejects_corrupt_data_set() {
+        use crate::model_file::{Model, ModelError};
+
+        let data_set: DataSet = format!("{}1,red\n2,blue", HEADER).parse().unwrap();
+        let read_back = |data_set: &DataSet| {
+            let mut bytes = Vec::new();
+            data_set.write_to(&mut bytes).unwrap();
+            DataSet::read_from(bytes.as_slice())
+        };
+        assert_eq!(read_back(&data_set).unwrap(), data_set);
+
+        let mut short_column = data_set.clone();
+        short_column.attributes[0].data = Data::Numeric([1.0].into_iter().collect());
+        assert!(matches!(
+            read_back(&short_column),
+            Err(ModelError::Format(_))
+        ));
+
+        let mut bad_id = data_set.clone();
+        match &mut bad_id.attributes[1].data {
+            Data::Nominal(nominal) => nominal.data = Ids::U8(vec![0, 2]),
+            _ => unreachable!(),
+        }
+        assert!(matches!(read_back(&bad_id), Err(ModelError::Format(_))));
 
-        Data::Nominal(Box::new(Nominal::new(fields)))
+        let mut bad_weights = data_set.clone();
+        bad_weights.weights = vec![2.0];
+        assert!(matches!(
+            read_back(&bad_weights),
+            Err(ModelError::Format(_))
+        ));
     }
 }
@@ -0,0 +1,463 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use itertools::Itertools;
+
+use super::writer::bag_text;
+use super::{ArffError, Attribute, Data, DataSet, Nominal, Numeric};
+
+/// How CSV files are read and written
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quote: char,
+    /// Whether the first row holds the attribute names, None to guess.
+    /// A file with a single row is guessed to be data since there's nothing to compare it with.
+    /// Columns are named att1, att2, ... when there's no header
+    pub header: Option<bool>,
+    /// Columns that aren't all numbers become nominal if they have at most this many distinct values
+    /// and strings otherwise
+    pub nominal_threshold: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            header: None,
+            nominal_threshold: 20,
+        }
+    }
+}
+
+impl DataSet {
+    /// Reads a CSV file, the relation is named after the file.
+    /// Empty fields and ? are missing values unless they're quoted
+    pub fn import_csv(file_path: &str, options: &CsvOptions) -> Result<Self, ArffError> {
+        let raw_data = fs::read_to_string(file_path)?;
+        let relation = Path::new(file_path)
+            .file_stem()
+            .map_or("csv".into(), |stem| stem.to_string_lossy());
+        Self::from_csv(&relation, &raw_data, options)
+    }
+
+    /// Builds a data set from CSV text, guessing the type of every column
+    pub fn from_csv(
+        relation: &str,
+        raw_data: &str,
+        options: &CsvOptions,
+    ) -> Result<Self, ArffError> {
+        let mut records = split_records(raw_data, options)?;
+
+        let header = options.header.unwrap_or_else(|| has_header(&records));
+        let labels: Vec<String> = match header && !records.is_empty() {
+            true => {
+                let header = records.remove(0);
+                let mut seen = HashSet::new();
+                // Labels have to be unique so Target::Name can find the attribute
+                if let Some(field) = header.iter().find(|field| !seen.insert(&field.text)) {
+                    return Err(ArffError::InvalidHeader {
+                        line: field.line,
+                        text: field.text.clone(),
+                        reason: "Duplicate attribute label",
+                    });
+                }
+                header.into_iter().map(|field| field.text).collect()
+            }
+            false => (1..=records.first().map_or(0, Vec::len))
+                .map(|index| format!("att{}", index))
+                .collect(),
+        };
+
+        if let Some((line, record)) = records
+            .iter()
+            .map(|record| (record.first().map_or(0, |field| field.line), record))
+            .find(|(_, record)| record.len() != labels.len())
+        {
+            return Err(ArffError::WrongValueCount {
+                line,
+                expected: labels.len(),
+                found: record.len(),
+            });
+        }
+
+        let attributes = labels
+            .into_iter()
+            .enumerate()
+            .map(|(column, label)| {
+                let values: Vec<_> = records
+                    .iter()
+                    .map(|record| {
+                        let field = &record[column];
+                        Some(field.text.as_str()).filter(|_| !field.is_missing())
+                    })
+                    .collect();
+                let data = infer_column(&values, options.nominal_threshold);
+                Attribute { label, data }
+            })
+            .collect();
+
+        Ok(Self {
+            relation: relation.to_owned(),
+            attributes,
+            weights: Vec::new(),
+        })
+    }
+
+    /// Writes the data set to file_path as CSV with a header row (unless options.header is Some(false))
+    pub fn export_csv(&self, file_path: &str, options: &CsvOptions) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write_csv(&mut writer, options)?;
+        writer.flush()
+    }
+
+    /// Writes the data set as CSV, missing values are written as ?
+    pub fn write_csv<W: Write>(&self, mut writer: W, options: &CsvOptions) -> io::Result<()> {
+        let delimiter = options.delimiter.to_string();
+        if options.header != Some(false) {
+            let labels = self
+                .attributes
+                .iter()
+                .map(|attribute| quote(&attribute.label, options))
+                .join(&delimiter);
+            writeln!(writer, "{}", labels)?;
+        }
+        for row in 0..self.get_data_len() {
+            let line = self
+                .attributes
+                .iter()
+                .map(|attribute| match &attribute.data {
+                    Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => {
+                        data.get(row).map(|value| value.to_string())
+                    }
+                    Data::Nominal(nominal) => nominal.get(row).map(|value| quote(value, options)),
+                    Data::String(data) => data[row].as_deref().map(|value| quote(value, options)),
+                    Data::Date(dates) => dates
+                        .get(row)
+                        .map(|date| quote(&dates.get_format().format(&date), options)),
+                    // Same text as the ARFF value, one nested row per line
+                    Data::Relational(bags) => {
+                        bags.get(row).map(|bag| quote(&bag_text(bag), options))
+                    }
+                })
+                .map(|value| value.unwrap_or("?".to_owned()))
+                .join(&delimiter);
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// Text that's read as a missing value when it isn't quoted
+fn is_missing(value: &str) -> bool {
+    value.is_empty() || value == "?"
+}
+
+// Infinity and NaN parse as f32 but a column of them is more likely labels like "nan" or "inf"
+fn is_number(value: &str) -> bool {
+    value.parse::<f32>().is_ok_and(f32::is_finite)
+}
+
+struct Field {
+    // Line the record starts on
+    line: usize,
+    text: String,
+    quoted: bool,
+}
+
+impl Field {
+    // Quoting "?" or "" keeps it as text, which is how write_csv writes those values
+    fn is_missing(&self) -> bool {
+        !self.quoted && is_missing(&self.text)
+    }
+
+    fn is_blank(&self) -> bool {
+        !self.quoted && self.text.is_empty()
+    }
+}
+
+// Splits text into records of fields
+// Quoted fields can hold delimiters and line breaks, a doubled quote inside quotes is a literal quote
+// Unquoted fields have surrounding whitespace trimmed
+fn split_records(raw_data: &str, options: &CsvOptions) -> Result<Vec<Vec<Field>>, ArffError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = raw_data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                c if c == options.quote && chars.peek() == Some(&options.quote) => {
+                    chars.next();
+                    field.push(c);
+                }
+                c if c == options.quote => in_quotes = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            c if c == options.quote && field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+                in_quotes = true;
+            }
+            c if c == options.delimiter => {
+                record.push(finish_field(&mut field, &mut quoted, record_line))
+            }
+            '\n' => {
+                record.push(finish_field(&mut field, &mut quoted, record_line));
+                // Blank lines don't count as records
+                if !(record.len() == 1 && record[0].is_blank()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            '\r' if chars.peek() == Some(&'\n') => (),
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(ArffError::UnterminatedQuote { line: record_line });
+    }
+    record.push(finish_field(&mut field, &mut quoted, record_line));
+    if !(record.len() == 1 && record[0].is_blank()) {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn finish_field(field: &mut String, quoted: &mut bool, line: usize) -> Field {
+    let text = match *quoted {
+        true => std::mem::take(field),
+        false => std::mem::take(field).trim().to_owned(),
+    };
+    Field {
+        line,
+        text,
+        quoted: std::mem::take(quoted),
+    }
+}
+
+// The first record is a header if none of it is a number and it doesn't look like the rest of its column:
+// either the rest of the column is numeric or the value never shows up again.
+// A lone record doesn't have a column to compare with so it's data
+fn has_header(records: &[Vec<Field>]) -> bool {
+    let Some((first, rest)) = records.split_first() else {
+        return false;
+    };
+    if rest.is_empty() {
+        return false;
+    }
+    if first
+        .iter()
+        .any(|field| is_number(&field.text) || field.is_missing())
+    {
+        return false;
+    }
+    first.iter().enumerate().all(|(column, label)| {
+        let mut values = rest
+            .iter()
+            .filter_map(|record| record.get(column))
+            .filter(|field| !field.is_missing())
+            .map(|field| field.text.as_str());
+        values.clone().all(is_number) || values.all(|value| value != label.text)
+    })
+}
+
+// Numeric if every value is a number, nominal if there are few enough distinct values, string otherwise
+fn infer_column(values: &[Option<&str>], nominal_threshold: usize) -> Data {
+    let present = || values.iter().flatten();
+    if present().all(|value| is_number(value)) {
+        let mut numeric = Numeric::new();
+        values.iter().for_each(|value| match value {
+            Some(value) => numeric.push(value.parse().unwrap()),
+            None => numeric.push_missing(),
+        });
+        return Data::Numeric(numeric);
+    }
+
+    // Labels keep the order they first show up in
+    let mut seen = HashSet::new();
+    let labels: Vec<String> = present()
+        .filter(|value| seen.insert(**value))
+        .map(|value| value.to_string())
+        .collect();
+    if labels.len() <= nominal_threshold.min(Nominal::MAX_SIZE) {
+        let mut nominal = Nominal::new(labels);
+        values.iter().for_each(|value| match value {
+            // Every value was just added as a label
+            Some(value) => nominal.push(value).unwrap(),
+            None => nominal.push_missing(),
+        });
+        return Data::Nominal(Box::new(nominal));
+    }
+
+    Data::String(
+        values
+            .iter()
+            .map(|value| value.map(str::to_owned))
+            .collect(),
+    )
+}
+
+// Quotes the field if it holds anything that would change how it's read back
+fn quote(value: &str, options: &CsvOptions) -> String {
+    let needs_quotes = is_missing(value)
+        || value.trim() != value
+        || value.contains([options.delimiter, options.quote, '\n', '\r']);
+    match needs_quotes {
+        true => {
+            let quote = options.quote.to_string();
+            let escaped = value.replace(&quote, &quote.repeat(2));
+            format!("{}{}{}", quote, escaped, quote)
+        }
+        false => value.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+
+    #[test]
+    fn test_from_csv_infers_types() {
+        let text = "size,colour,note\n1.5,red,\"hello, world\"\n2,blue,b\n,red,c\n4,?,\"say \"\"hi\"\"\"\n";
+        let options = CsvOptions {
+            nominal_threshold: 2,
+            ..Default::default()
+        };
+        let data_set = DataSet::from_csv("test", text, &options).unwrap();
+        let attributes = data_set.get_attributes();
+        assert_eq!(attributes[0].label, "size");
+        assert!(matches!(attributes[0].get_data(), Data::Numeric(_)));
+        assert_eq!(attributes[1].assume_nominal().get_fields(), ["red", "blue"]);
+        assert!(matches!(attributes[2].get_data(), Data::String(_)));
+        assert_eq!(data_set.get_value(0, 2), DataEntry::Missing);
+        assert_eq!(data_set.get_value(1, 3), DataEntry::Missing);
+        match attributes[2].get_data() {
+            Data::String(data) => assert_eq!(data[3].as_deref(), Some("say \"hi\"")),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_from_csv_header_detection() {
+        let options = CsvOptions::default();
+        let data_set = DataSet::from_csv(
+            "test",
+            "1;2\n3;4",
+            &CsvOptions {
+                delimiter: ';',
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(data_set.get_data_len(), 2);
+        assert_eq!(data_set.get_attributes()[1].label, "att2");
+
+        let data_set = DataSet::from_csv("test", "a,b\nx,y\nz,y\n", &options).unwrap();
+        assert_eq!(data_set.get_data_len(), 2);
+
+        let data_set = DataSet::from_csv("test", "x,y\nz,y\n", &options).unwrap();
+        assert_eq!(data_set.get_data_len(), 2);
+
+        // A single row is data unless the caller says it's a header
+        let data_set = DataSet::from_csv("test", "red,small\n", &options).unwrap();
+        assert_eq!(data_set.get_data_len(), 1);
+        assert_eq!(data_set.get_attributes()[0].label, "att1");
+        let header = CsvOptions {
+            header: Some(true),
+            ..options.clone()
+        };
+        let data_set = DataSet::from_csv("test", "red,small\n", &header).unwrap();
+        assert_eq!(data_set.get_data_len(), 0);
+        assert_eq!(data_set.get_attributes()[1].label, "small");
+
+        let result = DataSet::from_csv("test", "size,colour,size\n1,red,2\n", &options);
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidHeader { line: 1, ref text, .. }) if text == "size"
+        ));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let options = CsvOptions {
+            delimiter: '\t',
+            quote: '\'',
+            header: Some(true),
+            nominal_threshold: 20,
+        };
+        // Nominal labels that look like numbers come back as numeric so compare the written text
+        let write = |data_set: &DataSet| {
+            let mut bytes = Vec::new();
+            data_set.write_csv(&mut bytes, &options).unwrap();
+            String::from_utf8(bytes).unwrap()
+        };
+        let data_set = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let text = write(&data_set);
+        let csv = DataSet::from_csv("lakes", &text, &options).unwrap();
+        assert_eq!(csv.get_data_len(), data_set.get_data_len());
+        assert_eq!(write(&csv), text);
+    }
+
+    #[test]
+    fn test_csv_quoted_missing_markers() {
+        let mut nominal = Nominal::new(vec!["?".to_owned(), "".to_owned(), "x".to_owned()]);
+        ["?", "", "x"]
+            .iter()
+            .for_each(|value| nominal.push(value).unwrap());
+        nominal.push_missing();
+        let data_set = DataSet {
+            relation: "test".to_owned(),
+            attributes: vec![Attribute {
+                label: "marker".to_owned(),
+                data: Data::Nominal(Box::new(nominal)),
+            }],
+            weights: Vec::new(),
+        };
+        let mut bytes = Vec::new();
+        data_set
+            .write_csv(&mut bytes, &CsvOptions::default())
+            .unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        let csv = DataSet::from_csv("test", &text, &CsvOptions::default()).unwrap();
+        assert_eq!(csv, data_set);
+    }
+
+    #[test]
+    fn test_csv_non_finite_labels() {
+        let data_set =
+            DataSet::from_csv("test", "x\nnan\ninf\n1\n", &CsvOptions::default()).unwrap();
+        assert_eq!(
+            data_set.get_attributes()[0].assume_nominal().get_fields(),
+            ["nan", "inf", "1"]
+        );
+    }
+
+    #[test]
+    fn test_csv_unterminated_quote() {
+        let result = DataSet::from_csv("test", "a,b\n1,\"oops\n", &CsvOptions::default());
+        assert!(matches!(
+            result,
+            Err(ArffError::UnterminatedQuote { line: 2 })
+        ));
+    }
+}
@@ -0,0 +1,542 @@
+use std::fmt::Write;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const MILLIS_PER_DAY: i64 = SECONDS_PER_DAY * 1000;
+// 2000-01-01T00:00:00 UTC, what learners measure dates from
+const LEARNER_EPOCH_MILLIS: i64 = 946_684_800_000;
+const MONTH_NAMES: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+// Counts from Sunday
+const DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+/// Milliseconds since 1970-01-01T00:00:00 UTC, negative for earlier dates, which is how Weka stores dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Date(i64);
+
+impl Date {
+    pub fn from_epoch_millis(millis: i64) -> Self {
+        Self(millis)
+    }
+
+    pub fn epoch_millis(&self) -> i64 {
+        self.0
+    }
+
+    /// Seconds since 1970-01-01T00:00:00 UTC
+    pub fn epoch_seconds(&self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+
+    /// Minutes since 2000-01-01T00:00:00 UTC, this is how learners see dates.
+    /// Values are f32 so whole minutes are exact between 1968 and 2031
+    /// and whole days are exact for about a thousand years either side, seconds are rounded off
+    pub fn learner_minutes(&self) -> f32 {
+        ((self.0 - LEARNER_EPOCH_MILLIS) as f64 / 60_000.0) as f32
+    }
+}
+
+/// Date pattern in the style of Java's SimpleDateFormat (which is what Weka uses).
+///
+/// Supported letters: y M d H k h K m s S a E Z X.
+/// Text between single quotes is matched literally and '' is a single quote.
+/// Times without a zone are read as UTC, and two digit years (yy) are placed between 1970 and 2069.
+/// Years outside that range are written in full so they don't read back as a different year
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateFormat {
+    pattern: String,
+    fields: Vec<Field>,
+}
+
+// Number in each variant is how many times the pattern letter was repeated
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Field {
+    Literal(String),
+    Year(usize),
+    // 3 or more letters means the month is written as a name
+    Month(usize),
+    Day(usize),
+    // H: 0-23
+    Hour(usize),
+    // k: 1-24
+    HourFrom1(usize),
+    // h: 1-12
+    Hour12(usize),
+    // K: 0-11
+    Hour12From0(usize),
+    Minute(usize),
+    Second(usize),
+    Millisecond(usize),
+    AmPm,
+    // Day of the week names are checked for but otherwise ignored
+    DayName(usize),
+    Zone,
+}
+
+impl Field {
+    fn is_number(&self) -> bool {
+        match self {
+            Self::Month(width) => *width < 3,
+            Self::Literal(_) | Self::AmPm | Self::DayName(_) | Self::Zone => false,
+            _ => true,
+        }
+    }
+}
+
+// Pieces of a date as they're read, turned into a Date at the end
+#[derive(Default)]
+struct Parts {
+    year: u64,
+    month: u8,
+    day: u8,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    millisecond: u64,
+    pm: Option<bool>,
+    offset_seconds: i64,
+}
+
+impl DateFormat {
+    /// Weka's default when an ARFF date attribute doesn't give a format
+    pub const ISO_8601: &'static str = "yyyy-MM-dd'T'HH:mm:ss";
+
+    pub fn new(pattern: &str) -> Result<Self, &'static str> {
+        let mut fields = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        let mut literal = String::new();
+
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                // '' outside of quotes is an escaped quote
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    literal.push('\'');
+                    continue;
+                }
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            literal.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => literal.push(c),
+                        None => return Err("Missing closing quote in date format"),
+                    }
+                }
+            } else if c.is_ascii_alphabetic() {
+                let mut width = 1;
+                while chars.peek() == Some(&c) {
+                    chars.next();
+                    width += 1;
+                }
+                if !literal.is_empty() {
+                    fields.push(Field::Literal(std::mem::take(&mut literal)));
+                }
+                fields.push(match c {
+                    'y' => Field::Year(width),
+                    'M' => Field::Month(width),
+                    'd' => Field::Day(width),
+                    'H' => Field::Hour(width),
+                    'k' => Field::HourFrom1(width),
+                    'h' => Field::Hour12(width),
+                    'K' => Field::Hour12From0(width),
+                    'm' => Field::Minute(width),
+                    's' => Field::Second(width),
+                    'S' => Field::Millisecond(width),
+                    'a' => Field::AmPm,
+                    'E' => Field::DayName(width),
+                    'Z' | 'X' => Field::Zone,
+                    _ => return Err("Unsupported letter in date format"),
+                });
+            } else {
+                literal.push(c);
+            }
+        }
+        if !literal.is_empty() {
+            fields.push(Field::Literal(literal));
+        }
+
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            fields,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns None if text doesn't match the pattern or isn't a real date
+    pub fn parse(&self, text: &str) -> Option<Date> {
+        let mut rest = text.trim();
+        let mut parts = Parts {
+            year: 1970,
+            month: 1,
+            day: 1,
+            ..Default::default()
+        };
+
+        for (index, field) in self.fields.iter().enumerate() {
+            // Numbers right next to each other (yyyyMMdd) have to be read at their exact width
+            let abutting = self
+                .fields
+                .get(index + 1)
+                .is_some_and(|next| next.is_number());
+            match field {
+                Field::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                Field::AmPm => {
+                    let (marker, remaining) = rest.split_at_checked(2)?;
+                    parts.pm = match marker.to_ascii_uppercase().as_str() {
+                        "AM" => Some(false),
+                        "PM" => Some(true),
+                        _ => return None,
+                    };
+                    rest = remaining;
+                }
+                Field::DayName(_) => {
+                    let remaining = rest.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+                    if remaining.len() == rest.len() {
+                        return None;
+                    }
+                    rest = remaining;
+                }
+                Field::Month(width) if *width >= 3 => {
+                    let length = rest
+                        .find(|c: char| !c.is_ascii_alphabetic())
+                        .unwrap_or(rest.len());
+                    let name = rest[..length].to_ascii_lowercase();
+                    // Short names are the first three letters of the full name
+                    let month = MONTH_NAMES.iter().position(|month| {
+                        *month == name || (name.len() == 3 && month.starts_with(&name))
+                    })?;
+                    parts.month = month as u8 + 1;
+                    rest = &rest[length..];
+                }
+                Field::Zone => {
+                    let (offset, remaining) = parse_zone(rest)?;
+                    parts.offset_seconds = offset;
+                    rest = remaining;
+                }
+                Field::Year(width)
+                | Field::Month(width)
+                | Field::Day(width)
+                | Field::Hour(width)
+                | Field::HourFrom1(width)
+                | Field::Hour12(width)
+                | Field::Hour12From0(width)
+                | Field::Minute(width)
+                | Field::Second(width)
+                | Field::Millisecond(width) => {
+                    let (value, remaining) = parse_number(rest, abutting.then_some(*width))?;
+                    let digits = rest.len() - remaining.len();
+                    rest = remaining;
+                    match field {
+                        Field::Year(2) if digits == 2 => {
+                            parts.year = if value < 70 {
+                                2000 + value
+                            } else {
+                                1900 + value
+                            }
+                        }
+                        Field::Year(_) => parts.year = value,
+                        Field::Month(_) => parts.month = u8::try_from(value).ok()?,
+                        Field::Day(_) => parts.day = u8::try_from(value).ok()?,
+                        Field::Hour(_) if value < 24 => parts.hour = value,
+                        Field::HourFrom1(_) if (1..=24).contains(&value) => parts.hour = value % 24,
+                        Field::Hour12(_) if (1..=12).contains(&value) => parts.hour = value % 12,
+                        Field::Hour12From0(_) if value < 12 => parts.hour = value,
+                        Field::Minute(_) if value < 60 => parts.minute = value,
+                        Field::Second(_) if value < 60 => parts.second = value,
+                        Field::Millisecond(_) if value < 1000 => parts.millisecond = value,
+                        _ => return None,
+                    }
+                }
+            }
+        }
+
+        if !rest.is_empty() {
+            return None;
+        }
+        if parts.pm == Some(true) && parts.hour < 12 {
+            parts.hour += 12;
+        }
+
+        let year = i64::try_from(parts.year).ok()?;
+        if !(1..=12).contains(&parts.month)
+            || parts.day < 1
+            || parts.day > days_in_month(year, parts.month)
+        {
+            return None;
+        }
+        // Years with a lot of digits overflow, which makes the text invalid rather than panicking
+        let day = days_from_civil(year, parts.month, parts.day)?;
+        let seconds = day.checked_mul(SECONDS_PER_DAY)?.checked_add(
+            (parts.hour * 3600 + parts.minute * 60 + parts.second) as i64 - parts.offset_seconds,
+        )?;
+        let millis = seconds
+            .checked_mul(1000)?
+            .checked_add(parts.millisecond as i64)?;
+        Some(Date(millis))
+    }
+}
+
+impl DateFormat {
+    /// Writes date using the pattern, the result can be read back with parse
+    pub fn format(&self, date: &Date) -> String {
+        let days = date.0.div_euclid(MILLIS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let millis_of_day = date.0.rem_euclid(MILLIS_PER_DAY);
+        let (hour, minute, second) = (
+            millis_of_day / 3_600_000,
+            millis_of_day / 60_000 % 60,
+            millis_of_day / 1000 % 60,
+        );
+        let millisecond = millis_of_day % 1000;
+        let month_name = MONTH_NAMES[month as usize - 1];
+
+        let mut text = String::new();
+        self.fields.iter().for_each(|field| {
+            // Writing to a String can't fail
+            let _ = match field {
+                Field::Literal(literal) => write!(text, "{}", literal),
+                Field::Year(2) if (1970..2070).contains(&year) => write!(text, "{:02}", year % 100),
+                Field::Year(2) => write!(text, "{}", year),
+                Field::Year(width) => write!(text, "{:0width$}", year, width = width),
+                Field::Month(3) => write!(text, "{}", capitalize(&month_name[..3])),
+                Field::Month(width) if *width > 3 => write!(text, "{}", capitalize(month_name)),
+                Field::Month(width) => write!(text, "{:0width$}", month, width = width),
+                Field::Day(width) => write!(text, "{:0width$}", day, width = width),
+                Field::Hour(width) => write!(text, "{:0width$}", hour, width = width),
+                Field::HourFrom1(width) => {
+                    let hour = if hour == 0 { 24 } else { hour };
+                    write!(text, "{:0width$}", hour, width = width)
+                }
+                Field::Hour12(width) => {
+                    let hour = if hour % 12 == 0 { 12 } else { hour % 12 };
+                    write!(text, "{:0width$}", hour, width = width)
+                }
+                Field::Hour12From0(width) => write!(text, "{:0width$}", hour % 12, width = width),
+                Field::Minute(width) => write!(text, "{:0width$}", minute, width = width),
+                Field::Second(width) => write!(text, "{:0width$}", second, width = width),
+                Field::Millisecond(width) => {
+                    write!(text, "{:0width$}", millisecond, width = width)
+                }
+                Field::AmPm => write!(text, "{}", if hour < 12 { "AM" } else { "PM" }),
+                Field::DayName(width) => {
+                    // 1970-01-01 was a Thursday
+                    let name = DAY_NAMES[(days + 4).rem_euclid(7) as usize];
+                    match width {
+                        1..=3 => write!(text, "{}", &name[..3]),
+                        _ => write!(text, "{}", name),
+                    }
+                }
+                // Dates are stored in UTC
+                Field::Zone => write!(text, "+0000"),
+            };
+        });
+        text
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        // The ISO pattern is known to be valid
+        Self::new(Self::ISO_8601).unwrap()
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar, using Howard Hinnant's
+// days_from_civil: years start in March so the leap day is at the end of the year.
+// None if the days don't fit in an i64
+fn days_from_civil(year: i64, month: u8, day: u8) -> Option<i64> {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146_097)?
+        .checked_add(day_of_era)?
+        .checked_sub(719_468)
+}
+
+// Inverse of days_from_civil, returns (year, month, day)
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u8;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u8;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn capitalize(word: &str) -> String {
+    word[..1].to_ascii_uppercase() + &word[1..]
+}
+
+// Reads a number off the front of text, exactly width digits if width is given
+// Returns the number and the rest of the text
+fn parse_number(text: &str, width: Option<usize>) -> Option<(u64, &str)> {
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let length = match width {
+        Some(width) if width <= digits => width,
+        Some(_) => return None,
+        None if digits > 0 => digits,
+        None => return None,
+    };
+    Some((text[..length].parse().ok()?, &text[length..]))
+}
+
+// Accepts Z, +hh, +hhmm and +hh:mm (or - instead of +)
+// Returns the offset from UTC in seconds
+fn parse_zone(text: &str) -> Option<(i64, &str)> {
+    if let Some(rest) = text.strip_prefix('Z') {
+        return Some((0, rest));
+    }
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let (hours, rest) = parse_number(&text[1..], Some(2))?;
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+    let (minutes, rest) = parse_number(rest, Some(2)).unwrap_or((0, rest));
+    Some((sign * (hours * 3600 + minutes * 60) as i64, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(pattern: &str, text: &str) -> Option<f64> {
+        DateFormat::new(pattern)
+            .unwrap()
+            .parse(text)
+            .map(|date| date.epoch_seconds())
+    }
+
+    #[test]
+    fn test_parse_iso_default() {
+        let format = DateFormat::default();
+        let date = format.parse("2001-04-03T12:12:12").unwrap();
+        assert_eq!(date.epoch_seconds(), 986_299_932.0);
+        assert_eq!(format.parse("2001-04-03"), None);
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        assert_eq!(
+            seconds("yyyy-MM-dd HH:mm:ss", "2001-04-03 12:12:12"),
+            Some(986_299_932.0)
+        );
+        assert_eq!(seconds("yyyyMMdd", "20010403"), Some(986_256_000.0));
+        assert_eq!(
+            seconds("dd MMM yy hh:mm a", "03 Apr 01 12:12 PM"),
+            Some(986_299_920.0)
+        );
+        assert_eq!(
+            seconds(
+                "EEE, d MMMM yyyy 'at' HH:mm:ss.SSS Z",
+                "Tue, 3 April 2001 at 14:12:12.500 +0200"
+            ),
+            Some(986_299_932.5)
+        );
+    }
+
+    #[test]
+    fn test_parse_before_1970() {
+        assert_eq!(seconds("yyyy-MM-dd", "1969-12-31"), Some(-86_400.0));
+        assert_eq!(
+            seconds("yyyy-MM-dd HH:mm:ss.SSS", "1969-12-31 23:59:59.500"),
+            Some(-0.5)
+        );
+        assert_eq!(seconds("dd/MM/yyyy", "29/02/1904"), Some(-2_077_747_200.0));
+        assert_eq!(seconds("yyyy-MM-dd", "1900-02-29"), None);
+
+        let format = DateFormat::new("EEEE d MMMM yyyy HH:mm:ss.SSS").unwrap();
+        let text = "Thursday 20 July 1944 13:45:07.250";
+        assert_eq!(format.format(&format.parse(text).unwrap()), text);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        [
+            (DateFormat::ISO_8601, "2001-04-03T12:12:12"),
+            ("dd MMM yy hh:mm a", "03 Apr 01 12:12 PM"),
+            (
+                "EEE, d MMMM yyyy 'at' HH:mm:ss.SSS Z",
+                "Tue, 3 April 2001 at 14:12:12.500 +0000",
+            ),
+            ("yyyyMMdd kk", "20010403 24"),
+            ("EEEE dd/MM/yyyy", "Tuesday 03/04/2001"),
+            // yy can't hold a year outside 1970 to 2069 so it's written in full
+            ("yy-MM-dd", "1969-12-31"),
+            ("dd/MM/yy", "01/01/2070"),
+        ]
+        .iter()
+        .for_each(|(pattern, text)| {
+            let format = DateFormat::new(pattern).unwrap();
+            assert_eq!(format.format(&format.parse(text).unwrap()), *text);
+        });
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(seconds("yyyy-MM-dd", "2001-13-03"), None);
+        assert_eq!(seconds("yyyy-MM-dd", "2001-02-30"), None);
+        assert_eq!(seconds("yyyy-MM-dd", "2001-04-00"), None);
+        assert_eq!(seconds("yyyy-MM-dd", "2001-04-03 extra"), None);
+        // Too many years for milliseconds to fit in an i64
+        assert_eq!(seconds("yyyy-MM-dd", "99999999999999999-01-01"), None);
+        assert_eq!(seconds("yyyy-MM-dd", "300000000-01-01"), None);
+        assert!(DateFormat::new("yyyy-QQ").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+use super::{Attribute, Data, DataSet, HeaderError, Nominal, Numeric, Target, TargetError};
+
+/// How unsupervised discretization picks the intervals of an attribute
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Binning {
+    /// This many intervals of the same width between the smallest and largest value
+    EqualWidth(usize),
+    /// This many intervals with close to the same weight of rows in each, like Weka's useEqualFrequency.
+    /// Rows with the same value always share an interval so skewed attributes can end up with fewer
+    EqualFrequency(usize),
+    /// Exactly these cut points
+    CutPoints(Vec<f64>),
+}
+
+/// Turns numeric (and date) attributes into nominal ones with intervals for values, labelled like Weka's:
+/// (-inf-a], (a-b], ..., (b-inf). An attribute without any cut points becomes the single value All.
+/// Cut points are learned from a training set then apply works on any data set with the same attributes
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Discretizer {
+    // Increasing cut points of every attribute, None for attributes that are left alone
+    cut_points: Vec<Option<Vec<f64>>>,
+    // Training attributes without any rows, data sets being discretized have to match
+    header: DataSet,
+}
+
+impl Discretizer {
+    /// Fayyad and Irani's entropy based discretization with the MDL stopping rule,
+    /// which is what Weka's supervised Discretize filter does by default.
+    /// Every numeric attribute except the target is discretized and the target has to be nominal.
+    /// Rows missing the attribute or the target don't help pick cut points, and rows count for their weight
+    pub fn supervised(data: &DataSet, target: impl Into<Target>) -> Result<Self, TargetError> {
+        let target = target.into().resolve_nominal(data)?;
+        let classes = data.attributes[target].assume_nominal();
+        let cut_points = data
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(column, attribute)| {
+                let values = attribute.as_numeric().filter(|_| column != target)?;
+                let mut rows: Vec<_> = values
+                    .iter()
+                    .zip(classes.iter())
+                    .enumerate()
+                    .filter_map(|(row, (value, class))| {
+                        Some((value? as f64, class? as usize, data.get_weight(row) as f64))
+                    })
+                    .collect();
+                rows.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+                Some(mdl_cut_points(&rows, classes.size()))
+            })
+            .collect();
+        Ok(Self {
+            cut_points,
+            header: data.header(),
+        })
+    }
+
+    /// Every numeric attribute is discretized the same way, e.g. Binning::EqualFrequency(10).
+    /// Missing values are ignored and rows count for their weight
+    pub fn unsupervised(data: &DataSet, binning: Binning) -> Self {
+        Self::unsupervised_with(data, |_| Some(binning.clone()))
+    }
+
+    /// binning picks how each numeric attribute is discretized, None leaves the attribute alone, e.g.
+    /// |attribute| match attribute.label.as_str() { "ph" => Some(Binning::CutPoints(vec![7.0])), _ => None }
+    pub fn unsupervised_with(
+        data: &DataSet,
+        mut binning: impl FnMut(&Attribute) -> Option<Binning>,
+    ) -> Self {
+        let cut_points = data
+            .attributes
+            .iter()
+            .map(|attribute| {
+                let values = attribute.as_numeric()?;
+                Some(match binning(attribute)? {
+                    Binning::EqualWidth(bins) => equal_width_cut_points(&values, bins),
+                    Binning::EqualFrequency(bins) => {
+                        equal_frequency_cut_points(&sorted_values(data, &values), bins)
+                    }
+                    Binning::CutPoints(mut cut_points) => {
+                        cut_points.sort_by(f64::total_cmp);
+                        cut_points.dedup();
+                        cut_points
+                    }
+                })
+            })
+            .collect();
+        Self {
+            cut_points,
+            header: data.header(),
+        }
+    }
+
+    /// Cut points of the attribute at column, None if it isn't discretized
+    pub fn cut_points(&self, column: usize) -> Option<&[f64]> {
+        self.cut_points.get(column)?.as_deref()
+    }
+
+    /// Copy of data with the discretized attributes replaced by nominal ones,
+    /// which can be saved as a nominal ARFF file with DataSet::export.
+    /// A value goes in the first interval whose upper bound it's less than or equal to
+    pub fn apply(&self, data: &DataSet) -> Result<DataSet, HeaderError> {
+        self.header.check_header(data)?;
+        let attributes = data
+            .attributes
+            .iter()
+            .zip(&self.cut_points)
+            .map(|(attribute, cut_points)| match cut_points {
+                Some(cut_points) => {
+                    let mut nominal = Nominal::new(interval_labels(cut_points));
+                    // Only numeric attributes get cut points
+                    attribute
+                        .as_numeric()
+                        .unwrap()
+                        .iter()
+                        .map(|value| value.map(|value| interval(cut_points, value as f64)))
+                        .for_each(|id| nominal.push_id(id));
+                    Attribute {
+                        label: attribute.label.clone(),
+                        data: Data::Nominal(Box::new(nominal)),
+                    }
+                }
+                None => attribute.clone(),
+            })
+            .collect();
+        Ok(DataSet {
+            relation: data.relation.clone(),
+            attributes,
+            weights: data.weights.clone(),
+        })
+    }
+}
+
+// Checks for discretizers read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl Discretizer {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.cut_points.len() != self.header.attributes.len() {
+            return Err(format!(
+                "Discretizer has cut points for {} attributes but there are {}",
+                self.cut_points.len(),
+                self.header.attributes.len()
+            ));
+        }
+        self.header
+            .attributes
+            .iter()
+            .zip(&self.cut_points)
+            .try_for_each(|(attribute, cut_points)| {
+                let Some(cut_points) = cut_points else {
+                    return Ok(());
+                };
+                let increasing = cut_points
+                    .windows(2)
+                    .all(|pair| pair[0].total_cmp(&pair[1]).is_lt());
+                match attribute.as_numeric().is_some()
+                    && increasing
+                    && cut_points.len() < Nominal::MAX_SIZE
+                {
+                    true => Ok(()),
+                    false => Err(format!(
+                        "Discretizer has invalid cut points for attribute '{}'",
+                        attribute.label
+                    )),
+                }
+            })
+    }
+}
+
+// Index of the interval value falls in
+fn interval(cut_points: &[f64], value: f64) -> u32 {
+    cut_points.partition_point(|cut_point| *cut_point < value) as u32
+}
+
+fn interval_labels(cut_points: &[f64]) -> Vec<String> {
+    if cut_points.is_empty() {
+        return vec!["All".to_owned()];
+    }
+    let bounds: Vec<_> = cut_points
+        .iter()
+        .map(|cut_point| format_bound(*cut_point))
+        .collect();
+    let first = format!("(-inf-{}]", bounds[0]);
+    let last = format!("({}-inf)", bounds[bounds.len() - 1]);
+    let middle = bounds
+        .windows(2)
+        .map(|pair| format!("({}-{}]", pair[0], pair[1]));
+    std::iter::once(first)
+        .chain(middle)
+        .chain(std::iter::once(last))
+        .collect()
+}
+
+// Weka prints bounds with at most 6 decimals and no trailing zeros
+fn format_bound(value: f64) -> String {
+    let text = format!("{:.6}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_owned(),
+        _ => text.to_owned(),
+    }
+}
+
+// (value, weight) of every row with a value, sorted by value
+fn sorted_values(data: &DataSet, values: &Numeric) -> Vec<(f64, f64)> {
+    let mut rows: Vec<_> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(row, value)| Some((value? as f64, data.get_weight(row) as f64)))
+        .collect();
+    rows.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+    rows
+}
+
+fn equal_width_cut_points(values: &Numeric, bins: usize) -> Vec<f64> {
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((f64::MAX, f64::MIN), |(min, max), value| {
+            (min.min(value as f64), max.max(value as f64))
+        });
+    // Every value is missing or the same
+    if min >= max {
+        return Vec::new();
+    }
+    let width = (max - min) / bins as f64;
+    (1..bins).map(|bin| min + width * bin as f64).collect()
+}
+
+// Walks the rows in order and cuts once an interval has its share of the weight.
+// If the previous place a cut could go is closer to the share it's used instead,
+// and the share is worked out again from what's left after every cut
+fn equal_frequency_cut_points(rows: &[(f64, f64)], bins: usize) -> Vec<f64> {
+    let mut cut_points = Vec::new();
+    let mut remaining: f64 = rows.iter().map(|(_, weight)| weight).sum();
+    let mut share = remaining / bins as f64;
+    let mut counter = 0.0;
+    // (index of the last row before the previous place a cut could go, counter there)
+    let mut last: Option<(usize, f64)> = None;
+    for (index, pair) in rows.windows(2).enumerate() {
+        if cut_points.len() + 1 >= bins {
+            break;
+        }
+        let (value, weight) = pair[0];
+        counter += weight;
+        remaining -= weight;
+        if value == pair[1].0 {
+            continue;
+        }
+        if counter < share {
+            last = Some((index, counter));
+            continue;
+        }
+        match last {
+            Some((last_index, last_counter)) if share - last_counter < counter - share => {
+                cut_points.push((rows[last_index].0 + rows[last_index + 1].0) / 2.0);
+                counter -= last_counter;
+                last = Some((index, counter));
+            }
+            _ => {
+                cut_points.push((value + pair[1].0) / 2.0);
+                counter = 0.0;
+                last = None;
+            }
+        }
+        share = (remaining + counter) / (bins - cut_points.len()) as f64;
+    }
+    cut_points
+}
+
+// rows are (value, class, weight) sorted by value.
+// Finds the cut with the lowest class entropy, keeps it if it passes the MDL test
+// then does the same on both sides of it
+fn mdl_cut_points(rows: &[(f64, usize, f64)], classes: usize) -> Vec<f64> {
+    let prior = class_counts(rows, classes);
+    let prior_entropy = entropy(&prior);
+
+    // (index of the last row left of the cut, entropy of the cut)
+    let mut best: Option<(usize, f64)> = None;
+    let mut left = vec![0.0; classes];
+    let mut right = prior.clone();
+    for (index, pair) in rows.windows(2).enumerate() {
+        let (value, class, weight) = pair[0];
+        left[class] += weight;
+        right[class] -= weight;
+        // Can only cut between different values
+        if value < pair[1].0 {
+            let split_entropy = split_entropy(&left, &right);
+            if best.is_none_or(|best| split_entropy < best.1) {
+                best = Some((index, split_entropy));
+            }
+        }
+    }
+    let (index, split_entropy) = match best {
+        Some(best) => best,
+        None => return Vec::new(),
+    };
+    let gain = prior_entropy - split_entropy;
+    if gain <= 0.0 {
+        return Vec::new();
+    }
+
+    // The MDL test: the information gained has to pay for describing the cut
+    let (lower, upper) = rows.split_at(index + 1);
+    let (left, right) = (class_counts(lower, classes), class_counts(upper, classes));
+    let total: f64 = prior.iter().sum();
+    let count = |counts: &[f64]| counts.iter().filter(|count| **count > 0.0).count() as f64;
+    let delta = (3f64.powf(count(&prior)) - 2.0).log2()
+        - (count(&prior) * prior_entropy
+            - count(&left) * entropy(&left)
+            - count(&right) * entropy(&right));
+    // Every row but the last could be a cut, like Weka without its better encoding option
+    let possible_cuts = (rows.len() - 1) as f64;
+    if gain <= (possible_cuts.log2() + delta) / total {
+        return Vec::new();
+    }
+
+    let mut cut_points = mdl_cut_points(lower, classes);
+    cut_points.push((rows[index].0 + rows[index + 1].0) / 2.0);
+    cut_points.extend(mdl_cut_points(upper, classes));
+    cut_points
+}
+
+// Total weight of every class
+fn class_counts(rows: &[(f64, usize, f64)], classes: usize) -> Vec<f64> {
+    let mut counts = vec![0.0; classes];
+    rows.iter()
+        .for_each(|(_, class, weight)| counts[*class] += weight);
+    counts
+}
+
+// Entropy in bits of the class distribution given by counts
+fn entropy(counts: &[f64]) -> f64 {
+    let total: f64 = counts.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    counts
+        .iter()
+        .filter(|count| **count > 0.0)
+        .map(|count| count / total)
+        .map(|probability| -probability * probability.log2())
+        .sum()
+}
+
+// Entropy of both sides of a cut, weighted by how much of the total each side has
+fn split_entropy(left: &[f64], right: &[f64]) -> f64 {
+    let left_total: f64 = left.iter().sum();
+    let right_total: f64 = right.iter().sum();
+    let total = left_total + right_total;
+    (left_total * entropy(left) + right_total * entropy(right)) / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+
+    #[test]
+    fn test_supervised() {
+        // Classes change at 3.5 and 7.5 so those should be the cuts, noise should be ignored
+        let rows: String = (1..=10)
+            .flat_map(|value| {
+                let class = match value {
+                    1..=3 => "a",
+                    4..=7 => "b",
+                    _ => "a",
+                };
+                std::iter::repeat_n(format!("{},{}\n", value, class), 4)
+            })
+            .collect();
+        let data: DataSet = format!(
+            "@relation test
+            @attribute x numeric
+            @attribute noise numeric
+            @attribute class {{a, b}}
+            @data
+            {}?,1,a\n",
+            rows.replace(',', ",1,")
+        )
+        .parse()
+        .unwrap();
+
+        let discretizer = Discretizer::supervised(&data, "class").unwrap();
+        assert_eq!(discretizer.cut_points(0), Some(&[3.5, 7.5][..]));
+        assert_eq!(discretizer.cut_points(1), Some(&[][..]));
+        assert_eq!(discretizer.cut_points(2), None);
+
+        let discretized = discretizer.apply(&data).unwrap();
+        let x = discretized.get_attributes()[0].assume_nominal();
+        assert_eq!(x.get_fields(), ["(-inf-3.5]", "(3.5-7.5]", "(7.5-inf)"]);
+        assert_eq!(x.get(0), Some("(-inf-3.5]"));
+        assert_eq!(x.get(12), Some("(3.5-7.5]"));
+        assert_eq!(x.get(39), Some("(7.5-inf)"));
+        assert_eq!(discretized.get_value(0, 40), DataEntry::Missing);
+        assert_eq!(
+            discretized.get_attributes()[1]
+                .assume_nominal()
+                .get_fields(),
+            ["All"]
+        );
+
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert!(discretizer.apply(&lakes).is_err());
+    }
+
+    #[test]
+    fn test_unsupervised() {
+        // Half the rows are 0 like the area and volume columns
+        let rows: String = (0..20)
+            .map(|row| format!("{},{}\n", row, row.max(10) - 10))
+            .collect();
+        let data: DataSet = format!(
+            "@relation test
+            @attribute even numeric
+            @attribute skewed numeric
+            @data
+            {}?,?\n",
+            rows
+        )
+        .parse()
+        .unwrap();
+
+        let equal_width = Discretizer::unsupervised(&data, Binning::EqualWidth(4));
+        assert_eq!(equal_width.cut_points(0), Some(&[4.75, 9.5, 14.25][..]));
+        assert_eq!(equal_width.cut_points(1), Some(&[2.25, 4.5, 6.75][..]));
+
+        // All 11 zeros have to share an interval so the rest get split up between the other 3
+        let equal_frequency = Discretizer::unsupervised(&data, Binning::EqualFrequency(4));
+        assert_eq!(equal_frequency.cut_points(0), Some(&[4.5, 9.5, 14.5][..]));
+        assert_eq!(equal_frequency.cut_points(1), Some(&[0.5, 3.5, 6.5][..]));
+
+        let mixed =
+            Discretizer::unsupervised_with(&data, |attribute| match attribute.label.as_str() {
+                "skewed" => Some(Binning::CutPoints(vec![5.0, 0.0, 5.0])),
+                _ => None,
+            });
+        assert_eq!(mixed.cut_points(0), None);
+        assert_eq!(mixed.cut_points(1), Some(&[0.0, 5.0][..]));
+
+        // Discretized data sets save and load as plain nominal ARFF
+        let discretized = mixed.apply(&data).unwrap();
+        let skewed = discretized.get_attributes()[1].assume_nominal();
+        assert_eq!(skewed.get_fields(), ["(-inf-0]", "(0-5]", "(5-inf)"]);
+        assert_eq!(skewed.get(15), Some("(0-5]"));
+        let mut arff = Vec::new();
+        discretized.write_arff(&mut arff).unwrap();
+        let loaded: DataSet = String::from_utf8(arff).unwrap().parse().unwrap();
+        assert_eq!(loaded.get_attributes(), discretized.get_attributes());
+    }
+}
@@ -0,0 +1,287 @@
+use super::{Attribute, Data, DataSet, HeaderError, Nominal, Numeric, Target, TargetError};
+
+/// How an Encoder turns a nominal attribute into numeric attributes
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encoding {
+    /// One 0/1 attribute per value, labelled <attribute>=<value> like Weka's NominalToBinary.
+    /// drop_first leaves out the first value's attribute so the rest aren't collinear,
+    /// which linear models need since the dropped one is 1 minus the sum of the others
+    OneHot { drop_first: bool },
+    /// The index of the value in the declaration, so the order the values are declared in matters
+    Ordinal,
+    /// The weighted mean of the target for rows with the value, pulled towards the mean of the whole
+    /// target as if smoothing extra rows had that mean. Values the training set doesn't use get the overall mean
+    TargetMean { smoothing: f64 },
+}
+
+/// Turns nominal attributes into numeric ones so linear and distance based models can use them.
+/// Learned from a training set then transform works on any data set with the same attributes.
+/// Missing values stay missing in every attribute they're encoded to
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Encoder {
+    columns: Vec<Column>,
+    // Training attributes without any rows, data sets being transformed have to match
+    header: DataSet,
+}
+
+// What happens to each attribute
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Column {
+    Keep,
+    OneHot { drop_first: bool },
+    Ordinal,
+    // Encoded value of every nominal value
+    TargetMean { means: Vec<f64> },
+}
+
+impl Encoder {
+    /// Every nominal attribute except the target becomes one 0/1 attribute per value
+    pub fn one_hot(
+        data: &DataSet,
+        target: impl Into<Target>,
+        drop_first: bool,
+    ) -> Result<Self, TargetError> {
+        Self::new(data, target, |_| Some(Encoding::OneHot { drop_first }))
+    }
+
+    /// Every nominal attribute except the target becomes the index of its value
+    pub fn ordinal(data: &DataSet, target: impl Into<Target>) -> Result<Self, TargetError> {
+        Self::new(data, target, |_| Some(Encoding::Ordinal))
+    }
+
+    /// Every nominal attribute except the target becomes the mean of the target for its value,
+    /// the target has to be numeric
+    pub fn target_mean(
+        data: &DataSet,
+        target: impl Into<Target>,
+        smoothing: f64,
+    ) -> Result<Self, TargetError> {
+        Self::new(data, target, |_| Some(Encoding::TargetMean { smoothing }))
+    }
+
+    /// encoding picks how each nominal attribute is encoded, None leaves the attribute alone.
+    /// The target is never encoded, and has to be numeric if any attribute uses Encoding::TargetMean
+    pub fn new(
+        data: &DataSet,
+        target: impl Into<Target>,
+        mut encoding: impl FnMut(&Attribute) -> Option<Encoding>,
+    ) -> Result<Self, TargetError> {
+        let target = target.into();
+        let target_index = target.resolve(data)?;
+        let columns = data
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(column, attribute)| {
+                let nominal = match &attribute.data {
+                    Data::Nominal(nominal) if column != target_index => nominal,
+                    _ => return Ok(Column::Keep),
+                };
+                Ok(match encoding(attribute) {
+                    None => Column::Keep,
+                    Some(Encoding::OneHot { drop_first }) => Column::OneHot { drop_first },
+                    Some(Encoding::Ordinal) => Column::Ordinal,
+                    Some(Encoding::TargetMean { smoothing }) => {
+                        let target = target.resolve_numeric(data)?;
+                        let values = data.attributes[target].as_numeric().unwrap();
+                        Column::TargetMean {
+                            means: target_means(data, nominal, &values, smoothing),
+                        }
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            columns,
+            header: data.header(),
+        })
+    }
+
+    /// Copy of data with the encoded attributes replaced by numeric ones, in the same place
+    pub fn transform(&self, data: &DataSet) -> Result<DataSet, HeaderError> {
+        self.header.check_header(data)?;
+        let attributes = data
+            .attributes
+            .iter()
+            .zip(&self.columns)
+            .flat_map(|(attribute, column)| match column {
+                Column::Keep => vec![attribute.clone()],
+                Column::OneHot { drop_first } => {
+                    let nominal = attribute.assume_nominal();
+                    let skip = *drop_first as usize;
+                    nominal
+                        .get_fields()
+                        .iter()
+                        .enumerate()
+                        .skip(skip)
+                        .map(|(id, field)| Attribute {
+                            label: format!("{}={}", attribute.label, field),
+                            data: encode(nominal, |value| (value == id as u32) as u8 as f64),
+                        })
+                        .collect()
+                }
+                Column::Ordinal => vec![Attribute {
+                    label: attribute.label.clone(),
+                    data: encode(attribute.assume_nominal(), f64::from),
+                }],
+                Column::TargetMean { means } => vec![Attribute {
+                    label: attribute.label.clone(),
+                    data: encode(attribute.assume_nominal(), |value| means[value as usize]),
+                }],
+            })
+            .collect();
+        Ok(DataSet {
+            relation: data.relation.clone(),
+            attributes,
+            weights: data.weights.clone(),
+        })
+    }
+}
+
+// Checks for encoders read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl Encoder {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.columns.len() != self.header.attributes.len() {
+            return Err(format!(
+                "Encoder has {} columns but there are {} attributes",
+                self.columns.len(),
+                self.header.attributes.len()
+            ));
+        }
+        self.header
+            .attributes
+            .iter()
+            .zip(&self.columns)
+            .try_for_each(|(attribute, column)| {
+                let valid = match (column, &attribute.data) {
+                    (Column::Keep, _) => true,
+                    (Column::TargetMean { means }, Data::Nominal(nominal)) => {
+                        means.len() == nominal.size()
+                    }
+                    (_, Data::Nominal(_)) => true,
+                    _ => false,
+                };
+                match valid {
+                    true => Ok(()),
+                    false => Err(format!(
+                        "Encoder can't encode attribute '{}'",
+                        attribute.label
+                    )),
+                }
+            })
+    }
+}
+
+// Numeric column with the encoded value of every entry of nominal
+fn encode(nominal: &Nominal, encoded: impl Fn(u32) -> f64) -> Data {
+    let mut numeric = Numeric::new();
+    nominal.iter().for_each(|value| match value {
+        Some(value) => numeric.push(encoded(value) as f32),
+        None => numeric.push_missing(),
+    });
+    Data::Numeric(numeric)
+}
+
+// Smoothed mean of the target for every value of nominal, rows missing either are left out
+fn target_means(data: &DataSet, nominal: &Nominal, target: &Numeric, smoothing: f64) -> Vec<f64> {
+    // (weighted sum of the target, total weight) for each value
+    let mut sums = vec![(0.0, 0.0); nominal.size()];
+    let (mut total, mut total_weight) = (0.0, 0.0);
+    for (row, (value, target)) in nominal.iter().zip(target.iter()).enumerate() {
+        let target = match target {
+            Some(target) => target as f64,
+            None => continue,
+        };
+        let weight = data.get_weight(row) as f64;
+        total += weight * target;
+        total_weight += weight;
+        if let Some(value) = value {
+            sums[value as usize].0 += weight * target;
+            sums[value as usize].1 += weight;
+        }
+    }
+    let mean = match total_weight > 0.0 {
+        true => total / total_weight,
+        false => 0.0,
+    };
+    sums.iter()
+        .map(|(sum, weight)| match weight + smoothing > 0.0 {
+            true => (sum + smoothing * mean) / (weight + smoothing),
+            false => mean,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data_set::DataEntry,
+        linear::{LinearError, LinearModel},
+    };
+
+    #[test]
+    fn test_encoders() {
+        let data: DataSet = "@relation test
+            @attribute size {small, medium, large}
+            @attribute price numeric
+            @data
+            small,1
+            medium,2
+            large,6
+            large,4
+            ?,3"
+        .parse()
+        .unwrap();
+
+        let one_hot = Encoder::one_hot(&data, "price", false)
+            .unwrap()
+            .transform(&data)
+            .unwrap();
+        let labels: Vec<_> = one_hot
+            .get_attributes()
+            .iter()
+            .map(|attribute| attribute.label.as_str())
+            .collect();
+        assert_eq!(labels, ["size=small", "size=medium", "size=large", "price"]);
+        assert_eq!(one_hot.get_value(2, 2), DataEntry::Numeric(1.0));
+        assert_eq!(one_hot.get_value(0, 2), DataEntry::Numeric(0.0));
+        assert_eq!(one_hot.get_value(1, 4), DataEntry::Missing);
+
+        let drop_first = Encoder::one_hot(&data, "price", true).unwrap();
+        assert_eq!(drop_first.transform(&data).unwrap().get_len(), 3);
+
+        let ordinal = Encoder::ordinal(&data, "price").unwrap();
+        let encoded = ordinal.transform(&data).unwrap();
+        assert_eq!(encoded.get_value(0, 1), DataEntry::Numeric(1.0));
+        assert_eq!(encoded.get_value(0, 4), DataEntry::Missing);
+
+        // large has a mean of 5, the overall mean is 3.2 and smoothing 2 adds 2 rows of 3.2
+        let target_mean = Encoder::target_mean(&data, "price", 2.0).unwrap();
+        let encoded = target_mean.transform(&data).unwrap();
+        let large = encoded.get_value(0, 2).assume_numeric();
+        assert!((large - (10.0 + 6.4) / 4.0).abs() < 1e-6);
+
+        // Linear models refuse nominal attributes, once they're encoded they train fine
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert!(matches!(
+            LinearModel::new(&lakes, "latitude"),
+            Err(LinearError::NotNumeric { .. })
+        ));
+        assert!(matches!(
+            Encoder::target_mean(&lakes, "ph", 2.0),
+            Err(TargetError::WrongType { .. })
+        ));
+        let encoder = Encoder::one_hot(&lakes, "latitude", true).unwrap();
+        let encoded = encoder.transform(&lakes).unwrap();
+        assert!(encoded
+            .get_attributes()
+            .iter()
+            .all(|attribute| attribute.as_numeric().is_some()));
+        assert!(LinearModel::new(&encoded, "latitude").is_ok());
+    }
+}
@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{Attribute, Data, DataSet};
+
+impl DataSet {
+    /// Checks that other has the same attributes in the same order:
+    /// same labels, same types and nominal attributes declaring the same values in the same order.
+    /// Relation names and date formats don't matter
+    pub fn check_header(&self, other: &DataSet) -> Result<(), HeaderError> {
+        if self.get_len() != other.get_len() {
+            return Err(HeaderError::AttributeCount {
+                expected: self.get_len(),
+                found: other.get_len(),
+            });
+        }
+        self.attributes
+            .iter()
+            .zip(&other.attributes)
+            .enumerate()
+            .try_for_each(|(column, (expected, found))| check_attribute(column, expected, found))
+    }
+
+    /// The attributes without any rows, what other data sets get checked or aligned against
+    pub fn header(&self) -> DataSet {
+        self.empty_like()
+    }
+
+    /// Copy of self rearranged to have exactly the attributes of header, e.g. a test set
+    /// with its attributes in another order or its nominal values declared in another order.
+    /// Attributes are matched up by label and put in header's order, attributes header doesn't have are dropped.
+    /// Nominal values are matched by label and given header's ids.
+    /// Numeric, real and integer attributes can stand in for each other, other types have to match
+    pub fn align_to(&self, header: &DataSet) -> Result<DataSet, HeaderError> {
+        let attributes = header
+            .attributes
+            .iter()
+            .map(|expected| {
+                let found = self.attribute_by_name(&expected.label).ok_or_else(|| {
+                    HeaderError::MissingAttribute {
+                        attribute: expected.label.clone(),
+                    }
+                })?;
+                Ok(Attribute {
+                    label: expected.label.clone(),
+                    data: align_column(expected, found)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(DataSet {
+            relation: self.relation.clone(),
+            attributes,
+            weights: self.weights.clone(),
+        })
+    }
+}
+
+// found's entries in a column of the same type as expected
+fn align_column(expected: &Attribute, found: &Attribute) -> Result<Data, HeaderError> {
+    let mut data = expected.data.empty_like();
+    match (&mut data, &found.data) {
+        (Data::Nominal(nominal), Data::Nominal(other)) => {
+            // Id in other -> id in nominal
+            let ids: Vec<_> = other
+                .get_fields()
+                .iter()
+                .map(|field| nominal.get_map().get(field).copied())
+                .collect();
+            for row in 0..other.len() {
+                let id = match other.get_id(row) {
+                    Some(id) => {
+                        Some(ids[id as usize].ok_or_else(|| HeaderError::UnknownValue {
+                            attribute: expected.label.clone(),
+                            value: other.get_fields()[id as usize].clone(),
+                        })?)
+                    }
+                    None => None,
+                };
+                nominal.push_id(id);
+            }
+        }
+        (Data::Relational(bags), Data::Relational(other)) => {
+            for bag in other.iter() {
+                let bag = bag.map(|bag| bag.align_to(bags.get_header())).transpose()?;
+                bags.push_bag(bag);
+            }
+        }
+        (data, other)
+            if (is_numeric(data) && is_numeric(other)) || data.type_name() == other.type_name() =>
+        {
+            (0..other.len()).for_each(|row| data.push_from(other, row))
+        }
+        (data, other) => {
+            return Err(HeaderError::Type {
+                attribute: expected.label.clone(),
+                expected: data.type_name(),
+                found: other.type_name(),
+            })
+        }
+    }
+    Ok(data)
+}
+
+fn is_numeric(data: &Data) -> bool {
+    matches!(data, Data::Numeric(_) | Data::Real(_) | Data::Integer(_))
+}
+
+fn check_attribute(
+    column: usize,
+    expected: &Attribute,
+    found: &Attribute,
+) -> Result<(), HeaderError> {
+    if expected.label != found.label {
+        return Err(HeaderError::Label {
+            column,
+            expected: expected.label.clone(),
+            found: found.label.clone(),
+        });
+    }
+    match (&expected.data, &found.data) {
+        (Data::Nominal(lhs), Data::Nominal(rhs)) if lhs.get_fields() != rhs.get_fields() => {
+            Err(HeaderError::NominalValues {
+                attribute: expected.label.clone(),
+            })
+        }
+        (Data::Relational(lhs), Data::Relational(rhs)) => {
+            lhs.get_header().check_header(rhs.get_header())
+        }
+        (lhs, rhs) if lhs.type_name() != rhs.type_name() => Err(HeaderError::Type {
+            attribute: expected.label.clone(),
+            expected: lhs.type_name(),
+            found: rhs.type_name(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// How two data sets that should have the same attributes differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    AttributeCount {
+        expected: usize,
+        found: usize,
+    },
+    /// Attributes at the same column have different labels
+    Label {
+        column: usize,
+        expected: String,
+        found: String,
+    },
+    Type {
+        attribute: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// Nominal attributes that declare different values (or the same values in another order)
+    NominalValues {
+        attribute: String,
+    },
+    /// An attribute of the header that the data set doesn't have
+    MissingAttribute {
+        attribute: String,
+    },
+    /// A nominal value used by the data set that the header doesn't declare
+    UnknownValue {
+        attribute: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AttributeCount { expected, found } => {
+                write!(f, "Expected {} attributes but found {}", expected, found)
+            }
+            Self::Label {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Attribute {} should be '{}' but is '{}'",
+                column, expected, found
+            ),
+            Self::Type {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Attribute '{}' should be {} but is {}",
+                attribute, expected, found
+            ),
+            Self::MissingAttribute { attribute } => {
+                write!(f, "There's no attribute named '{}'", attribute)
+            }
+            Self::UnknownValue { attribute, value } => write!(
+                f,
+                "Attribute '{}' doesn't declare the value '{}'",
+                attribute, value
+            ),
+            Self::NominalValues { attribute } => write!(
+                f,
+                "Attribute '{}' declares different nominal values",
+                attribute
+            ),
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+
+    #[test]
+    fn test_align_to() {
+        let train: DataSet = "@relation train
+            @attribute size numeric
+            @attribute colour {red, blue, green}
+            @attribute class {yes, no}
+            @data
+            1,red,yes"
+            .parse()
+            .unwrap();
+        // Same attributes in another order, colour declared in another order and an extra attribute
+        let test: DataSet = "@relation test
+            @attribute class {no, yes}
+            @attribute extra string
+            @attribute colour {green, red, blue}
+            @attribute size integer
+            @data
+            yes,a,blue,3
+            no,b,?,4"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            train.check_header(&test),
+            Err(HeaderError::AttributeCount {
+                expected: 3,
+                found: 4
+            })
+        ));
+
+        let aligned = test.align_to(&train).unwrap();
+        assert_eq!(train.check_header(&aligned), Ok(()));
+        assert_eq!(aligned.get_value(0, 1), DataEntry::Numeric(4.0));
+        assert_eq!(aligned.get_value(1, 0), DataEntry::Nominal(1));
+        assert_eq!(aligned.get_value(1, 1), DataEntry::Missing);
+        assert_eq!(aligned.get_value(2, 0), DataEntry::Nominal(0));
+        assert_eq!(aligned.get_value(2, 1), DataEntry::Nominal(1));
+
+        let unknown: DataSet = "@relation test
+            @attribute size numeric
+            @attribute colour {red, pink}
+            @attribute class {yes, no}
+            @data
+            1,pink,yes"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            unknown.align_to(&train),
+            Err(HeaderError::UnknownValue {
+                attribute: "colour".to_owned(),
+                value: "pink".to_owned()
+            })
+        );
+        let missing = unknown.drop_attributes(["colour"]).unwrap();
+        assert!(matches!(
+            missing.align_to(&train),
+            Err(HeaderError::MissingAttribute { .. })
+        ));
+    }
+}
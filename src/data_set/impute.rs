@@ -0,0 +1,500 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{Attribute, Data, DataEntry, DataSet, HeaderError, Target, TargetError};
+use crate::{
+    knn::{distances, missing_penalties},
+    weighted_majority_vote_ordered,
+};
+
+/// Fills in missing values of numeric, real, integer and nominal attributes, other types are left alone.
+/// Learned from a training set then transform works on any data set with the same attributes.
+/// Rows count for their weight and ties between nominal values go to the value declared last
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Imputer {
+    strategy: Strategy,
+    // Training attributes without any rows, data sets being imputed have to match
+    header: DataSet,
+}
+
+// Fill values are DataEntry::Missing for attributes that aren't imputed
+// or that don't have a single value to learn from
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Strategy {
+    // Same fill value for every row
+    Constant(Vec<DataEntry>),
+    // Fill values for each value of the target, rows missing the target use overall
+    ByClass {
+        target: usize,
+        by_class: Vec<Vec<DataEntry>>,
+        overall: Vec<DataEntry>,
+    },
+    // Values of the k nearest training rows that have the value
+    Nearest {
+        k: usize,
+        data: DataSet,
+        missing_penalty: Vec<f32>,
+    },
+}
+
+/// A data set with its missing values filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imputed {
+    pub data_set: DataSet,
+    /// How many values were filled in for each attribute
+    pub filled: Vec<usize>,
+}
+
+impl Imputer {
+    /// Missing numeric values become the mean and missing nominal values become the mode
+    pub fn mean(data: &DataSet) -> Self {
+        let rows: Vec<_> = (0..data.get_data_len()).collect();
+        Self {
+            strategy: Strategy::Constant(fill_values(data, &rows, mean)),
+            header: data.header(),
+        }
+    }
+
+    /// Missing numeric values become the median and missing nominal values become the mode
+    pub fn median(data: &DataSet) -> Self {
+        let rows: Vec<_> = (0..data.get_data_len()).collect();
+        Self {
+            strategy: Strategy::Constant(fill_values(data, &rows, median)),
+            header: data.header(),
+        }
+    }
+
+    /// Mean and mode of the training rows with the same target value as the row being filled in,
+    /// rows with a missing target get the mean and mode of every row. The target has to be nominal
+    /// and test sets need their target values, so this is best kept to filling in training sets
+    pub fn class_conditional(
+        data: &DataSet,
+        target: impl Into<Target>,
+    ) -> Result<Self, TargetError> {
+        let target = target.into().resolve_nominal(data)?;
+        let classes = data.attributes[target].assume_nominal();
+        let mut class_rows = vec![Vec::new(); classes.size()];
+        classes
+            .iter()
+            .enumerate()
+            .filter_map(|(row, class)| Some((row, class?)))
+            .for_each(|(row, class)| class_rows[class as usize].push(row));
+        let rows: Vec<_> = (0..data.get_data_len()).collect();
+        Ok(Self {
+            strategy: Strategy::ByClass {
+                target,
+                by_class: class_rows
+                    .iter()
+                    .map(|rows| fill_values(data, rows, mean))
+                    .collect(),
+                overall: fill_values(data, &rows, mean),
+            },
+            header: data.header(),
+        })
+    }
+
+    /// Mean (or weighted vote for nominal attributes) of the k nearest training rows
+    /// that have the missing value. Distances are the same as KNN's and use every
+    /// numeric, date and nominal attribute, so scale the data first if attributes have very different ranges.
+    /// Dates only count towards the distance, they aren't filled in
+    pub fn nearest_neighbors(data: &DataSet, k: usize) -> Result<Self, ImputeError> {
+        if k == 0 {
+            return Err(ImputeError::NoNeighbors);
+        }
+        Ok(Self {
+            strategy: Strategy::Nearest {
+                k,
+                data: data.clone(),
+                missing_penalty: missing_penalties(data),
+            },
+            header: data.header(),
+        })
+    }
+
+    /// Copy of data with its missing values filled in, along with how many were filled in
+    pub fn transform(&self, data: &DataSet) -> Result<Imputed, HeaderError> {
+        self.header.check_header(data)?;
+        let imputed: Vec<_> = data.attributes.iter().map(is_imputed).collect();
+        // Fill values of the rows that are missing something
+        let fills: Vec<_> = (0..data.get_data_len())
+            .map(|row| {
+                let missing = (0..data.get_len())
+                    .any(|column| imputed[column] && data.get_value(column, row).is_missing());
+                match missing {
+                    true => Some(self.row_fills(data, row, &imputed)),
+                    false => None,
+                }
+            })
+            .collect();
+
+        let mut filled = vec![0; data.get_len()];
+        let attributes = data
+            .attributes
+            .iter()
+            .enumerate()
+            .map(|(column, attribute)| {
+                if !imputed[column] {
+                    return attribute.clone();
+                }
+                let mut column_data = attribute.data.empty_like();
+                for (row, fill) in fills.iter().enumerate() {
+                    let fill = fill
+                        .as_ref()
+                        .map_or(DataEntry::Missing, |fill| fill[column]);
+                    match attribute.get_value(row).is_missing() && !fill.is_missing() {
+                        true => {
+                            push_entry(&mut column_data, fill);
+                            filled[column] += 1;
+                        }
+                        false => column_data.push_from(&attribute.data, row),
+                    }
+                }
+                Attribute {
+                    label: attribute.label.clone(),
+                    data: column_data,
+                }
+            })
+            .collect();
+        Ok(Imputed {
+            data_set: DataSet {
+                relation: data.relation.clone(),
+                attributes,
+                weights: data.weights.clone(),
+            },
+            filled,
+        })
+    }
+
+    // Fill value of every attribute for row of data
+    fn row_fills(&self, data: &DataSet, row: usize, imputed: &[bool]) -> Vec<DataEntry> {
+        match &self.strategy {
+            Strategy::Constant(fills) => fills.clone(),
+            Strategy::ByClass {
+                target,
+                by_class,
+                overall,
+            } => match data.get_value(*target, row) {
+                DataEntry::Nominal(class) => by_class[class as usize].clone(),
+                _ => overall.clone(),
+            },
+            Strategy::Nearest {
+                k,
+                data: train,
+                missing_penalty,
+            } => {
+                let attributes: Vec<_> = (0..train.get_len())
+                    .filter(|column| {
+                        imputed[*column] || matches!(train.attributes[*column].data, Data::Date(_))
+                    })
+                    .collect();
+                let distances = distances(train, missing_penalty, &attributes, data, row);
+                let mut nearest: Vec<_> = (0..train.get_data_len()).collect();
+                nearest.sort_by(|lhs, rhs| distances[*lhs].total_cmp(&distances[*rhs]));
+                (0..train.get_len())
+                    .map(|column| {
+                        match imputed[column] && data.get_value(column, row).is_missing() {
+                            true => {
+                                let neighbors: Vec<_> = nearest
+                                    .iter()
+                                    .copied()
+                                    .filter(|index| !train.get_value(column, *index).is_missing())
+                                    .take(*k)
+                                    .collect();
+                                fill_value(train, column, &neighbors, mean)
+                            }
+                            false => DataEntry::Missing,
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// Checks for imputers read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl Imputer {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        match &self.strategy {
+            Strategy::Constant(fills) => check_fills(&self.header, fills),
+            Strategy::ByClass {
+                target,
+                by_class,
+                overall,
+            } => {
+                let classes = match self.header.attributes.get(*target).map(|a| &a.data) {
+                    Some(Data::Nominal(nominal)) => nominal.size(),
+                    _ => return Err("Imputer's target isn't a nominal attribute".to_owned()),
+                };
+                if by_class.len() != classes {
+                    return Err(format!(
+                        "Imputer has fill values for {} classes but the target has {}",
+                        by_class.len(),
+                        classes
+                    ));
+                }
+                by_class
+                    .iter()
+                    .chain(std::iter::once(overall))
+                    .try_for_each(|fills| check_fills(&self.header, fills))
+            }
+            Strategy::Nearest {
+                k,
+                data,
+                missing_penalty,
+            } => {
+                self.header.check_header(data).map_err(|error| {
+                    format!(
+                        "Imputer's training rows don't match its attributes: {}",
+                        error
+                    )
+                })?;
+                match *k > 0 && missing_penalty.len() == data.attributes.len() {
+                    true => Ok(()),
+                    false => Err("Imputer's neighbors don't match its attributes".to_owned()),
+                }
+            }
+        }
+    }
+}
+
+// Fill values have to be the same type as their attribute, nominal ones one of its values
+#[cfg(feature = "serde")]
+fn check_fills(header: &DataSet, fills: &[DataEntry]) -> Result<(), String> {
+    if fills.len() != header.attributes.len() {
+        return Err(format!(
+            "Imputer has {} fill values but there are {} attributes",
+            fills.len(),
+            header.attributes.len()
+        ));
+    }
+    header
+        .attributes
+        .iter()
+        .zip(fills)
+        .try_for_each(|(attribute, fill)| {
+            let valid = match (fill, &attribute.data) {
+                (DataEntry::Missing, _) => true,
+                (DataEntry::Numeric(_), Data::Numeric(_) | Data::Real(_) | Data::Integer(_)) => {
+                    true
+                }
+                (DataEntry::Nominal(id), Data::Nominal(nominal)) => (*id as usize) < nominal.size(),
+                _ => false,
+            };
+            match valid {
+                true => Ok(()),
+                false => Err(format!(
+                    "Imputer's fill value for attribute '{}' isn't one of its values",
+                    attribute.label
+                )),
+            }
+        })
+}
+
+/// Why an Imputer couldn't be made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImputeError {
+    /// nearest_neighbors was asked for 0 neighbors
+    NoNeighbors,
+}
+
+impl fmt::Display for ImputeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoNeighbors => write!(f, "k has to be at least 1"),
+        }
+    }
+}
+
+impl Error for ImputeError {}
+
+fn is_imputed(attribute: &Attribute) -> bool {
+    matches!(
+        attribute.data,
+        Data::Numeric(_) | Data::Real(_) | Data::Integer(_) | Data::Nominal(_)
+    )
+}
+
+// Fill value of every attribute learned from the given rows
+fn fill_values(
+    data: &DataSet,
+    rows: &[usize],
+    center: fn(&mut [(f64, f64)]) -> f64,
+) -> Vec<DataEntry> {
+    (0..data.get_len())
+        .map(|column| match is_imputed(&data.attributes[column]) {
+            true => fill_value(data, column, rows, center),
+            false => DataEntry::Missing,
+        })
+        .collect()
+}
+
+// center gets the (value, weight) of every row with a value for numeric attributes,
+// nominal attributes get the mode
+fn fill_value(
+    data: &DataSet,
+    column: usize,
+    rows: &[usize],
+    center: fn(&mut [(f64, f64)]) -> f64,
+) -> DataEntry {
+    let known = rows
+        .iter()
+        .map(|row| (data.get_value(column, *row), data.get_weight(*row)))
+        .filter(|(value, _)| !value.is_missing());
+    match data.attributes[column].data {
+        Data::Nominal(_) => {
+            let votes: Vec<_> = known
+                .map(|(value, weight)| (value.assume_nominal(), weight))
+                .collect();
+            match votes.is_empty() {
+                true => DataEntry::Missing,
+                false => DataEntry::Nominal(weighted_majority_vote_ordered(votes)),
+            }
+        }
+        _ => {
+            let mut values: Vec<_> = known
+                .map(|(value, weight)| (value.assume_numeric() as f64, weight as f64))
+                .collect();
+            match values.is_empty() {
+                true => DataEntry::Missing,
+                false => DataEntry::Numeric(center(&mut values) as f32),
+            }
+        }
+    }
+}
+
+fn mean(values: &mut [(f64, f64)]) -> f64 {
+    let total: f64 = values.iter().map(|(_, weight)| weight).sum();
+    values
+        .iter()
+        .map(|(value, weight)| value * weight)
+        .sum::<f64>()
+        / total
+}
+
+// Value the first half of the weight reaches, the middle of two values if it lands right between them
+fn median(values: &mut [(f64, f64)]) -> f64 {
+    values.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0));
+    let half = values.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+    let mut cumulative = 0.0;
+    for (index, (value, weight)) in values.iter().enumerate() {
+        cumulative += weight;
+        if cumulative > half {
+            return *value;
+        }
+        if cumulative == half && index + 1 < values.len() {
+            return (value + values[index + 1].0) / 2.0;
+        }
+    }
+    values[values.len() - 1].0
+}
+
+// entry has to be the same type as data
+fn push_entry(data: &mut Data, entry: DataEntry) {
+    match (data, entry) {
+        (
+            Data::Numeric(data) | Data::Real(data) | Data::Integer(data),
+            DataEntry::Numeric(value),
+        ) => data.push(value),
+        (Data::Nominal(nominal), DataEntry::Nominal(id)) => nominal.push_id(Some(id)),
+        _ => unreachable!("Fill values match their attribute's type"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imputers() {
+        let train: DataSet = "@relation train
+            @attribute x numeric
+            @attribute colour {red, blue}
+            @attribute class {a, b}
+            @attribute name string
+            @data
+            1,red,a,p
+            2,red,a,q
+            3,?,a,r
+            ?,blue,b,s
+            10,blue,b,?
+            12,?,b,t"
+            .parse()
+            .unwrap();
+
+        let mean = Imputer::mean(&train).transform(&train).unwrap();
+        assert_eq!(mean.filled, [1, 2, 0, 0]);
+        assert_eq!(mean.data_set.get_value(0, 3), DataEntry::Numeric(5.6));
+        // red and blue tie so the last declared wins
+        assert_eq!(mean.data_set.get_value(1, 2), DataEntry::Nominal(1));
+        assert!(mean.data_set.get_attributes()[3]
+            .get_data()
+            .eq(train.get_attributes()[3].get_data()));
+
+        let median = Imputer::median(&train).transform(&train).unwrap();
+        assert_eq!(median.data_set.get_value(0, 3), DataEntry::Numeric(3.0));
+
+        let by_class = Imputer::class_conditional(&train, "class").unwrap();
+        let imputed = by_class.transform(&train).unwrap();
+        assert_eq!(imputed.data_set.get_value(0, 3), DataEntry::Numeric(11.0));
+        assert_eq!(imputed.data_set.get_value(1, 2), DataEntry::Nominal(0));
+        assert_eq!(imputed.data_set.get_value(1, 5), DataEntry::Nominal(1));
+
+        // Closest rows with an x are the other blue b rows
+        let nearest = Imputer::nearest_neighbors(&train, 2).unwrap();
+        let imputed = nearest.transform(&train).unwrap();
+        assert_eq!(imputed.filled, [1, 2, 0, 0]);
+        assert_eq!(imputed.data_set.get_value(0, 3), DataEntry::Numeric(11.0));
+
+        // Test sets get filled in with what was learned from the training set
+        let test: DataSet = "@relation test
+            @attribute x numeric
+            @attribute colour {red, blue}
+            @attribute class {a, b}
+            @attribute name string
+            @data
+            ?,red,a,u
+            4,red,a,v"
+            .parse()
+            .unwrap();
+        let imputed = Imputer::mean(&train).transform(&test).unwrap();
+        assert_eq!(imputed.filled, [1, 0, 0, 0]);
+        assert_eq!(imputed.data_set.get_value(0, 0), DataEntry::Numeric(5.6));
+        let imputed = nearest.transform(&test).unwrap();
+        assert_eq!(imputed.data_set.get_value(0, 0), DataEntry::Numeric(1.5));
+
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert!(nearest.transform(&lakes).is_err());
+        assert_eq!(
+            Imputer::nearest_neighbors(&train, 0),
+            Err(ImputeError::NoNeighbors)
+        );
+    }
+
+    #[test]
+    fn test_nearest_neighbors_dates() {
+        let train: DataSet = "@relation dates
+            @attribute when date 'yyyy-MM-dd'
+            @attribute x numeric
+            @data
+            2020-01-01,1
+            2020-01-02,2
+            2020-06-01,10
+            2020-06-02,?
+            ?,?"
+        .parse()
+        .unwrap();
+        // The nearest date with an x is in June, a row without a date is as close to every row
+        // so it gets the first one, and dates are never filled in
+        let imputed = Imputer::nearest_neighbors(&train, 1)
+            .unwrap()
+            .transform(&train)
+            .unwrap();
+        assert_eq!(imputed.filled, [0, 2]);
+        assert_eq!(imputed.data_set.get_value(1, 3), DataEntry::Numeric(10.0));
+        assert_eq!(imputed.data_set.get_value(1, 4), DataEntry::Numeric(1.0));
+        assert_eq!(imputed.data_set.get_value(0, 4), DataEntry::Missing);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use super::{Attribute, Data, DataEntry, DataSet, Date};
+
+/// A single row of a DataSet. It only borrows the data set so making one is free,
+/// values are looked up in the columns when they're asked for
+#[derive(Debug, Clone, Copy)]
+pub struct Instance<'a> {
+    data_set: &'a DataSet,
+    index: usize,
+}
+
+/// Typed value of one attribute of an Instance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    /// Numeric, real and integer attributes
+    Numeric(f32),
+    /// The id is what learners see, the label is what's in the file
+    Nominal {
+        id: u32,
+        label: &'a str,
+    },
+    String(&'a str),
+    Date(Date),
+    /// Bag of instances of a relational attribute
+    Relational(&'a DataSet),
+    Missing,
+}
+
+impl<'a> Value<'a> {
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+
+    /// Numbers as they are and dates as minutes since 2000, like learners see them
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Numeric(value) => Some(*value),
+            Self::Date(date) => Some(date.learner_minutes()),
+            _ => None,
+        }
+    }
+
+    /// Nominal labels and strings
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::Nominal { label, .. } => Some(label),
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Instance<'a> {
+    /// Row number in the data set
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.data_set.get_weight(self.index)
+    }
+
+    /// Number of attributes
+    pub fn len(&self) -> usize {
+        self.data_set.get_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, attribute_index: usize) -> Value<'a> {
+        value(&self.data_set.attributes[attribute_index], self.index)
+    }
+
+    /// Value of the attribute with that label, None if there's no such attribute
+    pub fn get_by_name(&self, label: &str) -> Option<Value<'a>> {
+        self.data_set
+            .attribute_by_name(label)
+            .map(|attribute| value(attribute, self.index))
+    }
+
+    /// The value the way learners see it, same as DataSet::get_value
+    pub fn get_entry(&self, attribute_index: usize) -> DataEntry {
+        self.data_set.get_value(attribute_index, self.index)
+    }
+
+    /// (label, value) of every attribute in order
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, Value<'a>)> + 'a {
+        let index = self.index;
+        self.data_set
+            .attributes
+            .iter()
+            .map(move |attribute| (attribute.label.as_str(), value(attribute, index)))
+    }
+}
+
+// Same text as the row in an ARFF file
+impl fmt::Display for Instance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.data_set.row_text(self.index, false))
+    }
+}
+
+impl DataSet {
+    /// Panics if index is past the last row
+    pub fn row(&self, index: usize) -> Instance<'_> {
+        assert!(index < self.get_data_len(), "Row {} is out of range", index);
+        Instance {
+            data_set: self,
+            index,
+        }
+    }
+
+    /// Every row in order
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = Instance<'_>> + '_ {
+        (0..self.get_data_len()).map(move |index| Instance {
+            data_set: self,
+            index,
+        })
+    }
+
+    /// First attribute with that label
+    pub fn attribute_by_name(&self, label: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.label == label)
+    }
+
+    /// Column of the first attribute with that label
+    pub fn attribute_index(&self, label: &str) -> Option<usize> {
+        self.attributes
+            .iter()
+            .position(|attribute| attribute.label == label)
+    }
+}
+
+fn value(attribute: &Attribute, index: usize) -> Value<'_> {
+    let value = match &attribute.data {
+        Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => {
+            data.get(index).map(Value::Numeric)
+        }
+        Data::Nominal(nominal) => nominal.get_id(index).map(|id| Value::Nominal {
+            id,
+            label: &nominal.get_fields()[id as usize],
+        }),
+        Data::String(data) => data[index].as_deref().map(Value::String),
+        Data::Date(dates) => dates.get(index).map(Value::Date),
+        Data::Relational(bags) => bags.get(index).map(Value::Relational),
+    };
+    value.unwrap_or(Value::Missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let data_set: DataSet = "@relation test
+            @attribute size numeric
+            @attribute colour {red, blue}
+            @attribute note string
+            @data
+            1,blue,'hi there'
+            ?,red,?,{2}"
+            .parse()
+            .unwrap();
+        let rows: Vec<_> = data_set.rows().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get(0), Value::Numeric(1.0));
+        assert_eq!(
+            rows[0].get_by_name("colour"),
+            Some(Value::Nominal {
+                id: 1,
+                label: "blue"
+            })
+        );
+        assert_eq!(rows[0].get(2).as_str(), Some("hi there"));
+        assert_eq!(rows[0].get_entry(1), DataEntry::Nominal(1));
+        assert!(rows[1].get(0).is_missing());
+        assert_eq!(rows[1].weight(), 2.0);
+        assert_eq!(rows[1].get_by_name("shape"), None);
+
+        let labels: Vec<_> = data_set.row(1).iter().map(|(label, _)| label).collect();
+        assert_eq!(labels, ["size", "colour", "note"]);
+        assert_eq!(data_set.row(0).to_string(), "1,blue,'hi there'");
+        assert_eq!(data_set.attribute_index("note"), Some(2));
+    }
+}
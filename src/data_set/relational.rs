@@ -0,0 +1,157 @@
+use super::{ArffError, DataSet};
+
+/// Column of bags for multi-instance data.
+/// Every bag is a DataSet with the attributes declared between
+/// @attribute <name> relational and @end <name>
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Relational {
+    // Nested attributes without any rows, every bag starts out as a copy of this
+    header: DataSet,
+    bags: Vec<Option<DataSet>>,
+}
+
+impl Relational {
+    pub(super) fn new(label: &str) -> Self {
+        Self {
+            header: DataSet {
+                relation: label.to_owned(),
+                attributes: Vec::new(),
+                weights: Vec::new(),
+            },
+            bags: Vec::new(),
+        }
+    }
+
+    pub(super) fn header_mut(&mut self) -> &mut DataSet {
+        &mut self.header
+    }
+
+    pub(super) fn empty_like(&self) -> Self {
+        Self {
+            header: self.header.clone(),
+            bags: Vec::new(),
+        }
+    }
+
+    // value is the bag after unquoting, one nested row per line
+    pub(super) fn push(&mut self, value: &str, line: usize) -> Result<(), ArffError> {
+        let mut bag = self.header.empty_like();
+        for row in value.lines().map(str::trim).filter(|row| !row.is_empty()) {
+            bag.parse_row(row, line)?;
+        }
+        self.bags.push(Some(bag));
+        Ok(())
+    }
+
+    pub(super) fn push_empty(&mut self) {
+        self.bags.push(Some(self.header.empty_like()));
+    }
+
+    pub(super) fn push_missing(&mut self) {
+        self.bags.push(None);
+    }
+
+    // Bag has to have the same attributes as the header
+    pub(super) fn push_bag(&mut self, bag: Option<DataSet>) {
+        self.bags.push(bag);
+    }
+
+    pub fn len(&self) -> usize {
+        self.bags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bags.is_empty()
+    }
+
+    /// Returns the bag or None if the whole bag is missing
+    pub fn get(&self, index: usize) -> Option<&DataSet> {
+        self.bags[index].as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Option<&DataSet>> + '_ {
+        self.bags.iter().map(Option::as_ref)
+    }
+
+    pub fn is_missing(&self, index: usize) -> bool {
+        self.bags[index].is_none()
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.bags.iter().filter(|bag| bag.is_none()).count()
+    }
+
+    /// The nested attributes, the relation is named after the relational attribute
+    pub fn get_header(&self) -> &DataSet {
+        &self.header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::{Data, DataEntry};
+
+    const MUSK: &str = "@relation musk
+        @attribute molecule {m1, m2, m3}
+        @attribute bag RELATIONAL
+          @attribute f1 numeric
+          @attribute shape {round, 'flat, wide'}
+        @end bag
+        @attribute class {0, 1}
+        @data
+        m1,\"42,round\\n-3.5,'flat, wide'\",1
+        m2,?,0
+        m3,'',0";
+
+    #[test]
+    fn test_import_relational() {
+        let data_set: DataSet = MUSK.parse().unwrap();
+        let bags = match data_set.get_attributes()[1].get_data() {
+            Data::Relational(bags) => bags,
+            _ => unreachable!(),
+        };
+        assert_eq!(bags.get_header().get_len(), 2);
+        let bag = bags.get(0).unwrap();
+        assert_eq!(bag.get_data_len(), 2);
+        assert_eq!(bag.get_value(0, 1), DataEntry::Numeric(-3.5));
+        assert_eq!(bag.get_value(1, 1), DataEntry::Nominal(1));
+        assert!(bags.is_missing(1));
+        assert_eq!(bags.get(2).map(DataSet::get_data_len), Some(0));
+        assert_eq!(data_set.get_value(2, 0), DataEntry::Nominal(1));
+
+        let mut bytes = Vec::new();
+        data_set.write_arff(&mut bytes).unwrap();
+        let written: DataSet = String::from_utf8(bytes).unwrap().parse().unwrap();
+        assert_eq!(written, data_set);
+    }
+
+    #[test]
+    fn test_import_relational_errors() {
+        let result = "@relation r\n@attribute bag relational\n@attribute f numeric\n@data\n"
+            .parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidHeader { line: 4, .. })
+        ));
+        let result =
+            "@relation r\n@attribute bag relational\n@attribute f numeric\n".parse::<DataSet>();
+        assert!(
+            matches!(result, Err(ArffError::MissingEnd { ref attribute }) if attribute == "bag")
+        );
+        let result =
+            "@relation r\n@attribute bag relational\n@end other\n@data\n".parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidHeader { line: 3, .. })
+        ));
+        let result =
+            "@relation r\n@attribute bag relational\n@attribute f numeric\n@end bag\n@data\n'x'\n"
+                .parse::<DataSet>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidValue { line: 6, .. })
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+use super::{summary::quantile, Attribute, Data, DataSet, HeaderError, Numeric};
+
+/// Scalers move and stretch every numeric attribute: value -> (value - center) / scale.
+/// They're fit on a training set then transform any data set with the same attributes,
+/// so the training set and the test set get scaled the same way.
+/// Only numeric, real and integer attributes are scaled, missing values stay missing.
+/// Attributes where every value is the same (or missing) get a scale of 1 so nothing is divided by 0.
+/// Sparse attributes stay sparse when 0 is still 0 after scaling (a center of 0, e.g. a MinMaxScaler
+/// on non-negative values), otherwise they're stored densely since most of their entries stop being 0.
+/// Like the summary statistics, instance weights are ignored
+pub trait Scaler: Sized {
+    /// Learns the center and scale of every numeric attribute of data
+    fn fit(data: &DataSet) -> Self;
+
+    /// (center, scale) of the attribute at column, None if it isn't scaled
+    fn parameters(&self, column: usize) -> Option<(f64, f64)>;
+
+    /// Attributes the scaler was fit on, data sets being transformed have to match
+    fn header(&self) -> &DataSet;
+
+    /// Copy of data with every numeric attribute scaled
+    fn transform(&self, data: &DataSet) -> Result<DataSet, HeaderError> {
+        map_values(self, data, |value, (center, scale)| {
+            (value - center) / scale
+        })
+    }
+
+    /// Undoes transform, e.g. to get predictions of a scaled target back in the original units
+    fn inverse_transform(&self, data: &DataSet) -> Result<DataSet, HeaderError> {
+        map_values(self, data, |value, (center, scale)| value * scale + center)
+    }
+}
+
+/// Scales every numeric attribute to be between 0 and 1 on the training set:
+/// center is the minimum and scale is the maximum minus the minimum
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinMaxScaler {
+    parameters: Vec<Option<(f64, f64)>>,
+    header: DataSet,
+}
+
+impl Scaler for MinMaxScaler {
+    fn fit(data: &DataSet) -> Self {
+        Self {
+            parameters: fit_parameters(data, |values| {
+                let (min, max) = (values[0], values[values.len() - 1]);
+                (min, max - min)
+            }),
+            header: data.header(),
+        }
+    }
+
+    fn parameters(&self, column: usize) -> Option<(f64, f64)> {
+        *self.parameters.get(column)?
+    }
+
+    fn header(&self) -> &DataSet {
+        &self.header
+    }
+}
+
+/// Gives every numeric attribute a mean of 0 and a standard deviation of 1 on the training set,
+/// using the sample standard deviation like the summary does
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StandardScaler {
+    parameters: Vec<Option<(f64, f64)>>,
+    header: DataSet,
+}
+
+impl Scaler for StandardScaler {
+    fn fit(data: &DataSet) -> Self {
+        Self {
+            parameters: fit_parameters(data, |values| {
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let squares = values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>();
+                let std_dev = match values.len() {
+                    1 => 0.0,
+                    _ => (squares / (n - 1.0)).sqrt(),
+                };
+                (mean, std_dev)
+            }),
+            header: data.header(),
+        }
+    }
+
+    fn parameters(&self, column: usize) -> Option<(f64, f64)> {
+        *self.parameters.get(column)?
+    }
+
+    fn header(&self) -> &DataSet {
+        &self.header
+    }
+}
+
+/// Centers every numeric attribute on its median and divides by the interquartile range,
+/// so a few huge values (like the area of the biggest lakes) don't squash everything else
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RobustScaler {
+    parameters: Vec<Option<(f64, f64)>>,
+    header: DataSet,
+}
+
+impl Scaler for RobustScaler {
+    fn fit(data: &DataSet) -> Self {
+        Self {
+            parameters: fit_parameters(data, |values| {
+                let [q1, median, q3] = [0.25, 0.5, 0.75].map(|p| quantile(values, p));
+                (median, q3 - q1)
+            }),
+            header: data.header(),
+        }
+    }
+
+    fn parameters(&self, column: usize) -> Option<(f64, f64)> {
+        *self.parameters.get(column)?
+    }
+
+    fn header(&self) -> &DataSet {
+        &self.header
+    }
+}
+
+// Checks for scalers read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl MinMaxScaler {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        check_parameters(&self.parameters, &self.header)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl StandardScaler {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        check_parameters(&self.parameters, &self.header)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl RobustScaler {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        check_parameters(&self.parameters, &self.header)
+    }
+}
+
+// Numeric attributes are the ones with parameters, like fit_parameters gives them
+#[cfg(feature = "serde")]
+fn check_parameters(parameters: &[Option<(f64, f64)>], header: &DataSet) -> Result<(), String> {
+    let matches = parameters.len() == header.attributes.len()
+        && header
+            .attributes
+            .iter()
+            .zip(parameters)
+            .all(|(attribute, parameters)| {
+                let numeric = matches!(
+                    attribute.data,
+                    Data::Numeric(_) | Data::Real(_) | Data::Integer(_)
+                );
+                numeric == parameters.is_some()
+            });
+    match matches {
+        true => Ok(()),
+        false => Err("Scaler's parameters don't match its attributes".to_owned()),
+    }
+}
+
+// fit gets the sorted values of each numeric attribute and returns (center, scale)
+fn fit_parameters(
+    data: &DataSet,
+    mut fit: impl FnMut(&[f64]) -> (f64, f64),
+) -> Vec<Option<(f64, f64)>> {
+    data.attributes
+        .iter()
+        .map(|attribute| match &attribute.data {
+            Data::Numeric(values) | Data::Real(values) | Data::Integer(values) => {
+                let mut values: Vec<_> = values.iter().flatten().map(f64::from).collect();
+                values.sort_by(f64::total_cmp);
+                let (center, scale) = match values.is_empty() {
+                    true => (0.0, 1.0),
+                    false => fit(&values),
+                };
+                match scale > 0.0 {
+                    true => Some((center, scale)),
+                    false => Some((center, 1.0)),
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Scaled columns keep their type so the result still matches the header
+fn map_values(
+    scaler: &impl Scaler,
+    data: &DataSet,
+    map: impl Fn(f64, (f64, f64)) -> f64,
+) -> Result<DataSet, HeaderError> {
+    scaler.header().check_header(data)?;
+    let attributes = data
+        .attributes
+        .iter()
+        .enumerate()
+        .map(|(column, attribute)| {
+            let data = match (&attribute.data, scaler.parameters(column)) {
+                (Data::Numeric(values), Some(parameters)) => {
+                    Data::Numeric(map_column(values, parameters, &map))
+                }
+                (Data::Real(values), Some(parameters)) => {
+                    Data::Real(map_column(values, parameters, &map))
+                }
+                (Data::Integer(values), Some(parameters)) => {
+                    Data::Integer(map_column(values, parameters, &map))
+                }
+                (data, _) => data.clone(),
+            };
+            Attribute {
+                label: attribute.label.clone(),
+                data,
+            }
+        })
+        .collect();
+    Ok(DataSet {
+        relation: data.relation.clone(),
+        attributes,
+        weights: data.weights.clone(),
+    })
+}
+
+// Stays sparse if 0 maps to 0, otherwise every entry would be stored anyway
+fn map_column(
+    values: &Numeric,
+    parameters: (f64, f64),
+    map: impl Fn(f64, (f64, f64)) -> f64,
+) -> Numeric {
+    let mut scaled = match map(0.0, parameters) == 0.0 {
+        true => values.empty_like(),
+        false => Numeric::new(),
+    };
+    values.iter().for_each(|value| match value {
+        Some(value) => scaled.push(map(value as f64, parameters) as f32),
+        None => scaled.push_missing(),
+    });
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+
+    #[test]
+    fn test_scalers() {
+        let train: DataSet = "@relation train
+            @attribute x numeric
+            @attribute constant integer
+            @attribute class {a, b}
+            @data
+            1,5,a
+            2,5,b
+            3,5,a
+            ?,5,b
+            10,5,a"
+            .parse()
+            .unwrap();
+        let test: DataSet = "@relation test
+            @attribute x numeric
+            @attribute constant integer
+            @attribute class {a, b}
+            @data
+            19,5,b
+            ?,?,a"
+            .parse()
+            .unwrap();
+
+        let min_max = MinMaxScaler::fit(&train);
+        assert_eq!(min_max.parameters(0), Some((1.0, 9.0)));
+        assert_eq!(min_max.parameters(1), Some((5.0, 1.0)));
+        assert_eq!(min_max.parameters(2), None);
+        let scaled = min_max.transform(&train).unwrap();
+        assert_eq!(scaled.get_value(0, 4), DataEntry::Numeric(1.0));
+        assert_eq!(scaled.get_value(0, 3), DataEntry::Missing);
+        assert_eq!(scaled.get_value(1, 0), DataEntry::Numeric(0.0));
+        assert_eq!(scaled.get_value(2, 1), DataEntry::Nominal(1));
+        // Test sets are scaled with what was learned from the training set
+        let scaled_test = min_max.transform(&test).unwrap();
+        assert_eq!(scaled_test.get_value(0, 0), DataEntry::Numeric(2.0));
+        assert_eq!(min_max.inverse_transform(&scaled_test).unwrap(), test);
+
+        let standard = StandardScaler::fit(&train);
+        let (mean, std_dev) = standard.parameters(0).unwrap();
+        assert_eq!(mean, 4.0);
+        assert!((std_dev - 4.082).abs() < 1e-3);
+        let scaled = standard.transform(&train).unwrap();
+        assert_eq!(
+            scaled.get_value(0, 4),
+            DataEntry::Numeric((6.0 / std_dev) as f32)
+        );
+
+        let robust = RobustScaler::fit(&train);
+        assert_eq!(robust.parameters(0), Some((2.5, 3.0)));
+        let scaled = robust.transform(&train).unwrap();
+        assert_eq!(scaled.get_value(0, 4), DataEntry::Numeric(2.5));
+
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert!(robust.transform(&lakes).is_err());
+    }
+
+    #[test]
+    fn test_sparse_scaling() {
+        let text = "@relation sparse
+            @attribute centered numeric
+            @attribute counts numeric
+            @data
+            {0 -2, 1 4}
+            {}
+            {0 ?}
+            {0 2, 1 2}";
+        let data = DataSet::parse_arff(text, true).unwrap();
+        let is_sparse = |data: &DataSet, column: usize| {
+            data.get_attributes()[column].assume_numeric().is_sparse()
+        };
+
+        // The first column has a mean of 0 so its zeros stay zeros, the second doesn't
+        let standard = StandardScaler::fit(&data);
+        let scaled = standard.transform(&data).unwrap();
+        assert!(is_sparse(&scaled, 0));
+        assert!(!is_sparse(&scaled, 1));
+        assert_eq!(scaled.get_value(0, 1), DataEntry::Numeric(0.0));
+        assert_eq!(scaled.get_value(0, 2), DataEntry::Missing);
+        let restored = standard.inverse_transform(&scaled).unwrap();
+        assert!(is_sparse(&restored, 0));
+        assert_eq!(restored.get_attributes()[0], data.get_attributes()[0]);
+
+        // Counts have a minimum of 0
+        let min_max = MinMaxScaler::fit(&data);
+        let scaled = min_max.transform(&data).unwrap();
+        assert!(!is_sparse(&scaled, 0));
+        assert!(is_sparse(&scaled, 1));
+        assert_eq!(scaled.get_value(1, 0), DataEntry::Numeric(1.0));
+    }
+}
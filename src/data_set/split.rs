@@ -0,0 +1,171 @@
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::{DataSet, Target, TargetError};
+
+/// Row indices of a training set and a test set, both in the order the rows are in the data set.
+/// Use apply to turn them into data sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Split {
+    pub train: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl Split {
+    /// (training set, test set)
+    pub fn apply(&self, data_set: &DataSet) -> (DataSet, DataSet) {
+        (
+            data_set.take_rows(&self.train),
+            data_set.take_rows(&self.test),
+        )
+    }
+
+    // Fold fold of folds is the test set and the rest is the training set
+    fn from_folds(folds: &[Vec<usize>], fold: usize) -> Self {
+        let mut train: Vec<_> = folds
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != fold)
+            .flat_map(|(_, rows)| rows.iter().copied())
+            .collect();
+        train.sort_unstable();
+        let mut test = folds[fold].clone();
+        test.sort_unstable();
+        Self { train, test }
+    }
+}
+
+// Everything is shuffled with ChaCha8, whose output is fixed unlike StdRng's,
+// so the same seed gives the same split across platforms and versions of rand
+impl DataSet {
+    /// Random train/test split with round(rows * test_fraction) rows in the test set
+    pub fn holdout(&self, test_fraction: f32, seed: u64) -> Split {
+        assert!(
+            (0.0..=1.0).contains(&test_fraction),
+            "test_fraction has to be between 0 and 1"
+        );
+        let rows = self.shuffled_rows(seed);
+        let test_len = (rows.len() as f32 * test_fraction).round() as usize;
+        let (test, train) = rows.split_at(test_len);
+        let (mut train, mut test) = (train.to_vec(), test.to_vec());
+        train.sort_unstable();
+        test.sort_unstable();
+        Split { train, test }
+    }
+
+    /// k splits where every row is in exactly one test set.
+    /// Rows are shuffled then dealt out to the folds so fold sizes differ by at most 1
+    pub fn k_fold(&self, k: usize, seed: u64) -> Vec<Split> {
+        self.check_folds(k);
+        let rows = self.shuffled_rows(seed);
+        deal(&rows, k)
+    }
+
+    /// Same as k_fold but every fold gets close to the same share of each value of the target,
+    /// which has to be nominal. Works like Weka's StratifiedRemoveFolds:
+    /// rows are shuffled, grouped by target value and then dealt out to the folds.
+    /// Rows with a missing target value are dealt out last
+    pub fn stratified_k_fold(
+        &self,
+        k: usize,
+        target: impl Into<Target>,
+        seed: u64,
+    ) -> Result<Vec<Split>, TargetError> {
+        let target = target.into().resolve_nominal(self)?;
+        self.check_folds(k);
+        let nominal = self.attributes[target].assume_nominal();
+        let mut rows = self.shuffled_rows(seed);
+        // Stable sort so rows with the same target value stay shuffled
+        rows.sort_by_key(|row| nominal.get_id(*row).unwrap_or(u32::MAX));
+        Ok(deal(&rows, k))
+    }
+
+    /// One split per row with just that row in the test set
+    pub fn leave_one_out(&self) -> Vec<Split> {
+        let rows: Vec<_> = (0..self.get_data_len()).collect();
+        deal(&rows, rows.len())
+    }
+
+    fn shuffled_rows(&self, seed: u64) -> Vec<usize> {
+        let mut rows: Vec<_> = (0..self.get_data_len()).collect();
+        rows.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+        rows
+    }
+
+    fn check_folds(&self, k: usize) {
+        assert!(k >= 2, "Need at least 2 folds");
+        assert!(
+            k <= self.get_data_len(),
+            "Can't have more folds than instances"
+        );
+    }
+}
+
+// Row i goes to fold i % k
+fn deal(rows: &[usize], k: usize) -> Vec<Split> {
+    let mut folds = vec![Vec::new(); k];
+    rows.iter()
+        .enumerate()
+        .for_each(|(index, row)| folds[index % k].push(*row));
+    (0..k).map(|fold| Split::from_folds(&folds, fold)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits() {
+        let data_set = DataSet::import("./test_data/lakesDiscreteFold1.arff").unwrap();
+        let len = data_set.get_data_len();
+
+        let split = data_set.holdout(0.25, 42);
+        assert_eq!(split, data_set.holdout(0.25, 42));
+        assert_ne!(split, data_set.holdout(0.25, 7));
+        assert_eq!(split.test.len(), (len as f32 * 0.25).round() as usize);
+        assert_eq!(split.train.len() + split.test.len(), len);
+        let (train, test) = split.apply(&data_set);
+        assert_eq!(train.get_data_len(), split.train.len());
+        assert_eq!(test.get_value(3, 0), data_set.get_value(3, split.test[0]));
+
+        // Every row is tested exactly once
+        let folds = data_set.k_fold(10, 1);
+        let mut tested: Vec<_> = folds.iter().flat_map(|split| split.test.clone()).collect();
+        tested.sort_unstable();
+        assert_eq!(tested, (0..len).collect::<Vec<_>>());
+        assert!(folds
+            .iter()
+            .all(|split| split.train.len() + split.test.len() == len));
+
+        // Every fold has close to the same number of each target value
+        let ph = data_set.get_attributes()[18].assume_nominal();
+        let count = |rows: &[usize], id| {
+            rows.iter()
+                .filter(|row| ph.get_id(**row) == Some(id))
+                .count()
+        };
+        let folds = data_set.stratified_k_fold(5, "ph", 1).unwrap();
+        for id in 0..2 {
+            let counts: Vec<_> = folds.iter().map(|split| count(&split.test, id)).collect();
+            let min = counts.iter().min().unwrap();
+            let max = counts.iter().max().unwrap();
+            assert!(max - min <= 1, "{:?}", counts);
+        }
+        assert!(data_set.stratified_k_fold(5, "colour", 1).is_err());
+
+        let splits = data_set.leave_one_out();
+        assert_eq!(splits.len(), len);
+        assert_eq!(splits[3].test, [3]);
+    }
+
+    #[test]
+    fn test_shuffle_is_pinned() {
+        // Changing the generator or how it's used changes every split made with a seed
+        let data_set = DataSet::import("./test_data/lakesDiscreteFold1.arff").unwrap();
+        let rows = data_set
+            .take_rows(&(0..10).collect::<Vec<_>>())
+            .shuffled_rows(42);
+        assert_eq!(rows, [0, 3, 5, 7, 9, 4, 8, 1, 6, 2]);
+    }
+}
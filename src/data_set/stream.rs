@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use flate2::bufread::MultiGzDecoder;
+
+use super::{ArffError, DataSet};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Reads ARFF a chunk of rows at a time so files bigger than memory can be worked through.
+/// The header is parsed once up front and every chunk is a DataSet with the same attributes.
+/// Iterating gives chunks of chunk_size rows, 1 by default so it's a row at a time,
+/// and stops after the first error
+pub struct ArffReader<R: BufRead> {
+    reader: R,
+    // Attributes without any rows, every chunk starts out as a copy of this
+    header: DataSet,
+    chunk_size: usize,
+    // Reused between lines to save an allocation per line
+    line: String,
+    line_number: usize,
+    // Error hit after some rows of a chunk were read, it's returned by the next read instead
+    error: Option<ArffError>,
+    // Whether the iterator has returned an error
+    failed: bool,
+}
+
+impl ArffReader<Box<dyn BufRead>> {
+    /// Opens an ARFF file, gzip compressed files (.arff.gz) are decompressed as they're read
+    pub fn open(file_path: &str) -> Result<Self, ArffError> {
+        let mut file = BufReader::new(File::open(file_path)?);
+        // Checking the magic bytes rather than the extension.
+        // Files made of several gzip members (e.g. from cat a.gz b.gz) are read back to back
+        let reader: Box<dyn BufRead> = match file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            true => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+            false => Box::new(file),
+        };
+        Self::new(reader)
+    }
+}
+
+impl<R: BufRead> ArffReader<R> {
+    /// Parses the header, rows are only read when they're asked for
+    pub fn new(mut reader: R) -> Result<Self, ArffError> {
+        let mut line = String::new();
+        let mut line_number = 0;
+        let mut header_lines = Vec::new();
+        while next_line(&mut reader, &mut line, &mut line_number)? {
+            let line = line.trim();
+            header_lines.push((line_number, line.to_owned()));
+            let keyword = line.split_whitespace().next().unwrap_or_default();
+            if keyword.eq_ignore_ascii_case("@data") {
+                break;
+            }
+        }
+        let header = DataSet::parse_header(
+            &mut header_lines
+                .iter()
+                .map(|(line_number, line)| (*line_number, line.as_str())),
+        )?;
+
+        Ok(Self {
+            reader,
+            header,
+            chunk_size: 1,
+            line,
+            line_number,
+            error: None,
+            failed: false,
+        })
+    }
+
+    /// Numeric columns of every chunk only store their non-zero entries, see DataSet::import_sparse
+    pub fn sparse(mut self) -> Self {
+        self.header.use_sparse_columns();
+        self
+    }
+
+    /// Number of rows in each chunk when iterating
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "Chunks need at least one row");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// The attributes every chunk has, without any rows
+    pub fn header(&self) -> &DataSet {
+        &self.header
+    }
+
+    /// Reads up to max_rows rows into a new DataSet, None once every row has been read.
+    /// If a row can't be read the rows before it are returned and the error comes from the next call,
+    /// after that reading carries on from the line after the bad one
+    pub fn read_chunk(&mut self, max_rows: usize) -> Result<Option<DataSet>, ArffError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let mut chunk = self.header.empty_like();
+        let mut rows = 0;
+        match self.fill_chunk(&mut chunk, max_rows, &mut rows) {
+            Ok(()) => Ok((rows > 0).then_some(chunk)),
+            Err(error) if rows > 0 => {
+                self.error = Some(error);
+                // A bad value can leave part of its row in the columns before it
+                let rows: Vec<_> = (0..rows).collect();
+                Ok(Some(chunk.take_rows(&rows)))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // Parses rows into chunk until it has max_rows, rows counts the ones that were read fine
+    fn fill_chunk(
+        &mut self,
+        chunk: &mut DataSet,
+        max_rows: usize,
+        rows: &mut usize,
+    ) -> Result<(), ArffError> {
+        while *rows < max_rows
+            && next_line(&mut self.reader, &mut self.line, &mut self.line_number)?
+        {
+            chunk.parse_row(self.line.trim(), self.line_number)?;
+            *rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads every row that's left into one DataSet
+    pub fn read_all(mut self) -> Result<DataSet, ArffError> {
+        let header = self.header.empty_like();
+        let data_set = self.read_chunk(usize::MAX)?;
+        // Part of the file isn't good enough here
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(data_set.unwrap_or(header)),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArffReader<R> {
+    type Item = Result<DataSet, ArffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let chunk = self.read_chunk(self.chunk_size).transpose();
+        self.failed = matches!(chunk, Some(Err(_)));
+        chunk
+    }
+}
+
+// Reads the next line that isn't blank or a comment into line, false at the end of the input
+fn next_line<R: BufRead>(
+    reader: &mut R,
+    line: &mut String,
+    line_number: &mut usize,
+) -> io::Result<bool> {
+    loop {
+        line.clear();
+        if reader.read_line(line)? == 0 {
+            return Ok(false);
+        }
+        *line_number += 1;
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('%') {
+            return Ok(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let text = "@relation test\n@attribute size numeric\n@attribute colour {red, blue}\n\
+            @DATA\n1,red\n% comment\n\n2,blue\n3,red\n";
+        let chunks: Vec<_> = ArffReader::new(text.as_bytes())
+            .unwrap()
+            .with_chunk_size(2)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].get_data_len(), 2);
+        assert_eq!(chunks[1].get_value(0, 0), DataEntry::Numeric(3.0));
+
+        // Line numbers still count the lines that were skipped
+        let result = ArffReader::new(format!("{}big,red\n", text).as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(
+            result,
+            Err(ArffError::InvalidValue { line: 10, .. })
+        ));
+
+        // The good rows of a chunk come back before the error and nothing comes after it
+        let bad_row = format!("{}1,green\n4,blue\n", text);
+        let mut reader = ArffReader::new(bad_row.as_bytes())
+            .unwrap()
+            .with_chunk_size(10);
+        assert_eq!(reader.next().unwrap().unwrap().get_data_len(), 3);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(ArffError::UnknownNominal { line: 10, .. }))
+        ));
+        assert!(reader.next().is_none());
+        assert!(ArffReader::new(bad_row.as_bytes())
+            .unwrap()
+            .read_all()
+            .is_err());
+
+        let all = ArffReader::new(text.as_bytes())
+            .unwrap()
+            .read_all()
+            .unwrap();
+        assert_eq!(all, text.parse().unwrap());
+    }
+
+    #[test]
+    fn test_import_gzip() {
+        let path = std::env::temp_dir().join("arff_reader_test_import_gzip.arff.gz");
+        let text = std::fs::read("./test_data/lakesA1.arff").unwrap();
+        // Split into two members like concatenated .gz files
+        let (first, second) = text.split_at(text.len() / 2);
+        std::fs::write(&path, [gzip(first), gzip(second)].concat()).unwrap();
+        let data_set = DataSet::import(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            data_set,
+            DataSet::import("./test_data/lakesA1.arff").unwrap()
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::writer::bag_text;
+use super::{Attribute, Data, DataSet};
+
+/// Statistics of every attribute, see DataSet::summary.
+/// Display prints an overview table followed by the label frequencies of every nominal attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataSetSummary {
+    pub relation: String,
+    pub instances: usize,
+    pub attributes: Vec<AttributeSummary>,
+}
+
+/// Statistics of one attribute, about what Weka's Preprocess panel shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSummary {
+    pub label: String,
+    pub type_name: &'static str,
+    pub missing: usize,
+    /// Number of different known values
+    pub distinct: usize,
+    /// Number of known values that only show up once
+    pub unique: usize,
+    pub stats: Stats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stats {
+    /// Numeric, real, integer and date attributes (dates in minutes since 2000, see Date::learner_minutes).
+    /// None when every value is missing
+    Numeric(Option<NumericStats>),
+    /// One entry per declared label, in the order they're declared
+    Nominal(Vec<LabelFrequency>),
+    /// Strings and relational attributes only get the counts
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Sample standard deviation (divides by n - 1), 0 when there's only one value
+    pub std_dev: f32,
+    /// First quartile, median and third quartile, interpolated between values
+    pub quartiles: [f32; 3],
+    /// Equal width bins from min to max
+    pub histogram: Vec<Bin>,
+}
+
+/// Values from min up to max, the last bin includes max
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bin {
+    pub min: f32,
+    pub max: f32,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelFrequency {
+    pub label: String,
+    pub count: usize,
+    /// Sum of the instance weights, same as count when the data set isn't weighted
+    pub weight: f32,
+}
+
+impl DataSet {
+    /// Statistics of every attribute with 10 histogram bins for numeric attributes.
+    /// Everything but the nominal weights ignores instance weights
+    pub fn summary(&self) -> DataSetSummary {
+        self.summary_with_bins(10)
+    }
+
+    pub fn summary_with_bins(&self, bins: usize) -> DataSetSummary {
+        assert!(bins > 0, "Histograms need at least one bin");
+        DataSetSummary {
+            relation: self.relation.clone(),
+            instances: self.get_data_len(),
+            attributes: self
+                .attributes
+                .iter()
+                .map(|attribute| summarize(self, attribute, bins))
+                .collect(),
+        }
+    }
+}
+
+fn summarize(data_set: &DataSet, attribute: &Attribute, bins: usize) -> AttributeSummary {
+    let summary = |missing, distinct, unique, stats| AttributeSummary {
+        label: attribute.label.clone(),
+        type_name: attribute.data.type_name(),
+        missing,
+        distinct,
+        unique,
+        stats,
+    };
+    if let Some(data) = attribute.as_numeric() {
+        let mut values: Vec<_> = data.iter().flatten().collect();
+        values.sort_by(f32::total_cmp);
+        // Lengths of the runs of equal values
+        let runs: Vec<_> = values
+            .chunk_by(|a, b| a == b)
+            .map(|run| run.len())
+            .collect();
+        let unique = runs.iter().filter(|run| **run == 1).count();
+        let stats = Stats::Numeric(numeric_stats(&values, bins));
+        return summary(data.missing_count(), runs.len(), unique, stats);
+    }
+    match &attribute.data {
+        Data::Nominal(nominal) => {
+            let mut frequencies: Vec<_> = nominal
+                .get_fields()
+                .iter()
+                .map(|label| LabelFrequency {
+                    label: label.clone(),
+                    count: 0,
+                    weight: 0.0,
+                })
+                .collect();
+            nominal.iter().enumerate().for_each(|(row, id)| {
+                if let Some(id) = id {
+                    frequencies[id as usize].count += 1;
+                    frequencies[id as usize].weight += data_set.get_weight(row);
+                }
+            });
+            let distinct = frequencies.iter().filter(|label| label.count > 0).count();
+            let unique = frequencies.iter().filter(|label| label.count == 1).count();
+            summary(
+                nominal.missing_count(),
+                distinct,
+                unique,
+                Stats::Nominal(frequencies),
+            )
+        }
+        Data::String(data) => {
+            let (distinct, unique) = counts(data.iter().flatten().map(String::as_str));
+            let missing = data.iter().filter(|value| value.is_none()).count();
+            summary(missing, distinct, unique, Stats::Other)
+        }
+        // Bags are told apart by their ARFF text
+        Data::Relational(bags) => {
+            let texts: Vec<_> = bags.iter().flatten().map(bag_text).collect();
+            let (distinct, unique) = counts(texts.iter().map(String::as_str));
+            summary(bags.missing_count(), distinct, unique, Stats::Other)
+        }
+        _ => unreachable!("Numeric types were handled above"),
+    }
+}
+
+// (distinct, unique)
+fn counts<'a>(values: impl Iterator<Item = &'a str>) -> (usize, usize) {
+    let mut seen = HashSet::new();
+    let mut repeated = HashSet::new();
+    values.for_each(|value| {
+        if !seen.insert(value) {
+            repeated.insert(value);
+        }
+    });
+    (seen.len(), seen.len() - repeated.len())
+}
+
+// values has to be sorted
+fn numeric_stats(values: &[f32], bins: usize) -> Option<NumericStats> {
+    let (&min, &max) = (values.first()?, values.last()?);
+    let n = values.len() as f64;
+    let mean = values.iter().map(|value| *value as f64).sum::<f64>() / n;
+    let std_dev = match values.len() {
+        1 => 0.0,
+        _ => {
+            let squares = values
+                .iter()
+                .map(|value| (*value as f64 - mean).powi(2))
+                .sum::<f64>();
+            (squares / (n - 1.0)).sqrt()
+        }
+    };
+
+    let width = (max - min) / bins as f32;
+    let mut histogram: Vec<_> = (0..bins)
+        .map(|bin| Bin {
+            min: min + width * bin as f32,
+            max: min + width * (bin + 1) as f32,
+            count: 0,
+        })
+        .collect();
+    histogram[bins - 1].max = max;
+    values.iter().for_each(|value| {
+        // Every value lands in the first bin when they're all equal
+        let bin = match width > 0.0 {
+            true => (((value - min) / width) as usize).min(bins - 1),
+            false => 0,
+        };
+        histogram[bin].count += 1;
+    });
+
+    Some(NumericStats {
+        min,
+        max,
+        mean: mean as f32,
+        std_dev: std_dev as f32,
+        quartiles: [0.25, 0.5, 0.75].map(|p| quantile(values, p) as f32),
+        histogram,
+    })
+}
+
+// Linear interpolation between the closest ranks, values has to be sorted and not empty
+pub(super) fn quantile<T: Copy + Into<f64>>(values: &[T], p: f64) -> f64 {
+    let position = p * (values.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    let (below_value, above_value) = (values[below].into(), values[above].into());
+    below_value + (above_value - below_value) * (position - below as f64)
+}
+
+// Pads every column to its widest cell, the first left columns are left aligned and the rest right aligned
+pub(crate) fn write_table(
+    f: &mut fmt::Formatter,
+    rows: &[Vec<String>],
+    left: usize,
+    indent: &str,
+) -> fmt::Result {
+    let columns = rows.first().map_or(0, Vec::len);
+    let widths: Vec<_> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in rows {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| match column < left {
+                true => format!("{:<width$}", cell, width = width),
+                false => format!("{:>width$}", cell, width = width),
+            })
+            .collect();
+        writeln!(f, "{}{}", indent, cells.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+fn number(value: f32) -> String {
+    format!("{:.3}", value)
+}
+
+impl fmt::Display for DataSetSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Relation: {}", self.relation)?;
+        writeln!(f, "Instances: {}", self.instances)?;
+        writeln!(f, "Attributes: {}", self.attributes.len())?;
+        writeln!(f)?;
+
+        let header = [
+            "#", "Name", "Type", "Missing", "Distinct", "Unique", "Mean", "StdDev", "Min", "Q1",
+            "Median", "Q3", "Max",
+        ];
+        let mut rows = vec![header.iter().map(|cell| cell.to_string()).collect()];
+        rows.extend(
+            self.attributes
+                .iter()
+                .enumerate()
+                .map(|(index, attribute)| {
+                    let mut row = vec![
+                        (index + 1).to_string(),
+                        attribute.label.clone(),
+                        attribute.type_name.to_owned(),
+                        attribute.missing.to_string(),
+                        attribute.distinct.to_string(),
+                        attribute.unique.to_string(),
+                    ];
+                    match &attribute.stats {
+                        Stats::Numeric(Some(stats)) => row.extend(
+                            [stats.mean, stats.std_dev, stats.min]
+                                .iter()
+                                .chain(&stats.quartiles)
+                                .chain([&stats.max])
+                                .map(|value| number(*value)),
+                        ),
+                        _ => row.resize(header.len(), String::new()),
+                    }
+                    row
+                }),
+        );
+        // #, name and type are text
+        write_table(f, &rows, 3, "")?;
+
+        for attribute in &self.attributes {
+            if let Stats::Nominal(frequencies) = &attribute.stats {
+                writeln!(f)?;
+                writeln!(f, "{}:", attribute.label)?;
+                let mut rows = vec![vec!["Label".into(), "Count".into(), "Weight".into()]];
+                rows.extend(frequencies.iter().map(|frequency| {
+                    vec![
+                        frequency.label.clone(),
+                        frequency.count.to_string(),
+                        number(frequency.weight),
+                    ]
+                }));
+                write_table(f, &rows, 1, "  ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Everything about one attribute, with the histogram drawn as bars
+impl fmt::Display for AttributeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Name: {}  Type: {}", self.label, self.type_name)?;
+        writeln!(
+            f,
+            "Missing: {}  Distinct: {}  Unique: {}",
+            self.missing, self.distinct, self.unique
+        )?;
+        match &self.stats {
+            Stats::Numeric(Some(stats)) => {
+                let rows = [
+                    ("Minimum", stats.min),
+                    ("Maximum", stats.max),
+                    ("Mean", stats.mean),
+                    ("StdDev", stats.std_dev),
+                    ("Q1", stats.quartiles[0]),
+                    ("Median", stats.quartiles[1]),
+                    ("Q3", stats.quartiles[2]),
+                ]
+                .iter()
+                .map(|(name, value)| vec![name.to_string(), number(*value)])
+                .collect::<Vec<_>>();
+                write_table(f, &rows, 1, "  ")?;
+
+                let largest = stats.histogram.iter().map(|bin| bin.count).max();
+                let rows: Vec<_> = stats
+                    .histogram
+                    .iter()
+                    .map(|bin| {
+                        // Longest bar is 40 characters
+                        let bar = bin.count * 40 / largest.unwrap_or(1).max(1);
+                        vec![
+                            number(bin.min),
+                            number(bin.max),
+                            bin.count.to_string(),
+                            "#".repeat(bar),
+                        ]
+                    })
+                    .collect();
+                writeln!(f, "Histogram:")?;
+                write_table(f, &rows, 0, "  ")
+            }
+            Stats::Nominal(frequencies) => {
+                let rows: Vec<_> = frequencies
+                    .iter()
+                    .map(|frequency| {
+                        vec![
+                            frequency.label.clone(),
+                            frequency.count.to_string(),
+                            number(frequency.weight),
+                        ]
+                    })
+                    .collect();
+                write_table(f, &rows, 1, "  ")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let data_set: DataSet = "@relation test
+            @attribute size numeric
+            @attribute colour {red, blue, green}
+            @attribute note string
+            @data
+            1,red,a
+            2,red,b
+            3,blue,a
+            4,?,?
+            ?,red,c,{3}"
+            .parse()
+            .unwrap();
+        let summary = data_set.summary_with_bins(3);
+        assert_eq!(summary.instances, 5);
+
+        let size = &summary.attributes[0];
+        assert_eq!((size.missing, size.distinct, size.unique), (1, 4, 4));
+        let Stats::Numeric(Some(stats)) = &size.stats else {
+            unreachable!()
+        };
+        assert_eq!(stats.mean, 2.5);
+        assert!((stats.std_dev - 1.291).abs() < 1e-3);
+        assert_eq!(stats.quartiles, [1.75, 2.5, 3.25]);
+        let counts: Vec<_> = stats.histogram.iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, [1, 1, 2]);
+
+        let colour = &summary.attributes[1];
+        assert_eq!((colour.distinct, colour.unique), (2, 1));
+        let Stats::Nominal(frequencies) = &colour.stats else {
+            unreachable!()
+        };
+        assert_eq!(frequencies[0].count, 3);
+        assert_eq!(frequencies[0].weight, 5.0);
+        assert_eq!(frequencies[2].count, 0);
+
+        let note = &summary.attributes[2];
+        assert_eq!((note.missing, note.distinct, note.unique), (1, 3, 2));
+
+        let table = summary.to_string();
+        assert!(table.contains("Instances: 5"));
+        assert!(table.contains("2.500"));
+        assert!(size.to_string().contains("Histogram:"));
+    }
+}
@@ -0,0 +1,184 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{Data, DataSet};
+
+/// The attribute a learner predicts.
+/// Text is the label of the attribute, except that "first" and "last" (any case) work like
+/// Weka's -c option when no attribute has that exact label
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Target {
+    Index(usize),
+    Name(String),
+    First,
+    Last,
+}
+
+impl From<usize> for Target {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for Target {
+    fn from(text: &str) -> Self {
+        Self::Name(text.to_owned())
+    }
+}
+
+impl Target {
+    /// Column of the target in labels
+    pub fn resolve_labels<'a, I>(&self, labels: I) -> Result<usize, TargetError>
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut labels = labels.into_iter();
+        let len = labels.len();
+        match self {
+            Self::Index(index) if *index < len => Ok(*index),
+            Self::Index(index) => Err(TargetError::OutOfRange { index: *index, len }),
+            Self::Name(name) => {
+                let first = name.eq_ignore_ascii_case("first");
+                let last = name.eq_ignore_ascii_case("last");
+                match labels.position(|label| label == name) {
+                    Some(index) => Ok(index),
+                    None if (first || last) && len == 0 => Err(TargetError::NoAttributes),
+                    None if first => Ok(0),
+                    None if last => Ok(len - 1),
+                    None => Err(TargetError::NotFound { name: name.clone() }),
+                }
+            }
+            _ if len == 0 => Err(TargetError::NoAttributes),
+            Self::First => Ok(0),
+            Self::Last => Ok(len - 1),
+        }
+    }
+
+    /// Column of the target in data_set, any type of attribute is fine
+    pub fn resolve(&self, data_set: &DataSet) -> Result<usize, TargetError> {
+        self.resolve_labels(
+            data_set
+                .attributes
+                .iter()
+                .map(|attribute| attribute.label.as_str()),
+        )
+    }
+
+    /// Same as resolve but the target has to be nominal, which is what classifiers predict
+    pub fn resolve_nominal(&self, data_set: &DataSet) -> Result<usize, TargetError> {
+        let index = self.resolve(data_set)?;
+        match data_set.attributes[index].data {
+            Data::Nominal(_) => Ok(index),
+            _ => Err(wrong_type(data_set, index, "nominal")),
+        }
+    }
+
+    /// Same as resolve but the target has to be numeric (dates count), which is what regression predicts
+    pub fn resolve_numeric(&self, data_set: &DataSet) -> Result<usize, TargetError> {
+        let index = self.resolve(data_set)?;
+        match data_set.attributes[index].as_numeric() {
+            Some(_) => Ok(index),
+            None => Err(wrong_type(data_set, index, "numeric")),
+        }
+    }
+}
+
+fn wrong_type(data_set: &DataSet, index: usize, expected: &'static str) -> TargetError {
+    TargetError::WrongType {
+        attribute: data_set.attributes[index].label.clone(),
+        expected,
+    }
+}
+
+/// Why a Target couldn't be found in a data set, also used when picking attributes by Target
+/// (e.g. DataSet::select_attributes) so the messages talk about attributes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetError {
+    /// No attribute has that label
+    NotFound { name: String },
+    /// Index past the last attribute
+    OutOfRange { index: usize, len: usize },
+    /// First or last of a data set without attributes
+    NoAttributes,
+    /// The learner can't predict this type of attribute
+    WrongType {
+        attribute: String,
+        expected: &'static str,
+    },
+    /// Every row is missing the target so there's nothing to learn from
+    NoKnownValues { attribute: String },
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound { name } => write!(f, "No attribute is named '{}'", name),
+            Self::OutOfRange { index, len } => write!(
+                f,
+                "Attribute index {} is out of range, there are {} attributes",
+                index, len
+            ),
+            Self::NoAttributes => write!(f, "The data set doesn't have any attributes"),
+            Self::WrongType {
+                attribute,
+                expected,
+            } => write!(f, "Target '{}' needs to be {}", attribute, expected),
+            Self::NoKnownValues { attribute } => {
+                write!(f, "Target '{}' is missing in every row", attribute)
+            }
+        }
+    }
+}
+
+impl Error for TargetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_target() {
+        let data_set: DataSet = "@relation test
+            @attribute size numeric
+            @attribute colour {red, blue}
+            @attribute note string
+            @data
+            1,blue,hi"
+            .parse()
+            .unwrap();
+        assert_eq!(Target::from("colour").resolve(&data_set), Ok(1));
+        assert_eq!(Target::from("LAST").resolve(&data_set), Ok(2));
+        assert_eq!(Target::from("first").resolve_numeric(&data_set), Ok(0));
+        assert_eq!(Target::from(1).resolve_nominal(&data_set), Ok(1));
+        assert_eq!(
+            Target::from("shape").resolve(&data_set),
+            Err(TargetError::NotFound {
+                name: "shape".to_owned()
+            })
+        );
+        assert_eq!(
+            Target::from(3).resolve(&data_set),
+            Err(TargetError::OutOfRange { index: 3, len: 3 })
+        );
+        // An attribute named last is picked over the last attribute
+        let named_last: DataSet = "@relation test
+            @attribute last numeric
+            @attribute first numeric
+            @data
+            1,2"
+        .parse()
+        .unwrap();
+        assert_eq!(Target::from("last").resolve(&named_last), Ok(0));
+        assert_eq!(Target::from("First").resolve(&named_last), Ok(0));
+        assert_eq!(Target::Last.resolve(&named_last), Ok(1));
+        assert!(matches!(
+            Target::Last.resolve_nominal(&data_set),
+            Err(TargetError::WrongType {
+                expected: "nominal",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+use super::{
+    compact_weights, Attribute, Data, DataSet, HeaderError, Instance, Target, TargetError,
+};
+
+// None of these change the data set they're called on, they all build a new one
+
+impl DataSet {
+    /// Only the given attributes, in the order they're given.
+    /// Attributes are picked the same way as a learner's target: index, label, "first" or "last"
+    pub fn select_attributes<T: Into<Target>>(
+        &self,
+        attributes: impl IntoIterator<Item = T>,
+    ) -> Result<Self, TargetError> {
+        let columns = self.resolve_all(attributes)?;
+        Ok(Self {
+            relation: self.relation.clone(),
+            attributes: columns
+                .iter()
+                .map(|column| self.attributes[*column].clone())
+                .collect(),
+            weights: self.weights.clone(),
+        })
+    }
+
+    /// Every attribute except the given ones
+    pub fn drop_attributes<T: Into<Target>>(
+        &self,
+        attributes: impl IntoIterator<Item = T>,
+    ) -> Result<Self, TargetError> {
+        let columns = self.resolve_all(attributes)?;
+        Ok(Self {
+            relation: self.relation.clone(),
+            attributes: self
+                .attributes
+                .iter()
+                .enumerate()
+                .filter(|(column, _)| !columns.contains(column))
+                .map(|(_, attribute)| attribute.clone())
+                .collect(),
+            weights: self.weights.clone(),
+        })
+    }
+
+    /// Only the rows predicate returns true for, e.g.
+    /// data_set.filter(|row| row.get_by_name("type").and_then(|value| value.as_str()) == Some("lake"))
+    pub fn filter(&self, mut predicate: impl FnMut(Instance) -> bool) -> Self {
+        let rows: Vec<_> = self
+            .rows()
+            .filter(|row| predicate(*row))
+            .map(|row| row.index())
+            .collect();
+        self.take_rows(&rows)
+    }
+
+    /// The given rows in the given order, a row can show up more than once.
+    /// Panics if a row is out of range
+    pub fn take_rows(&self, rows: &[usize]) -> Self {
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|attribute| {
+                let mut data = attribute.data.empty_like();
+                rows.iter()
+                    .for_each(|row| data.push_from(&attribute.data, *row));
+                Attribute {
+                    label: attribute.label.clone(),
+                    data,
+                }
+            })
+            .collect();
+        let weights = match self.is_weighted() {
+            true => compact_weights(rows.iter().map(|row| self.get_weight(*row)).collect()),
+            false => Vec::new(),
+        };
+        Self {
+            relation: self.relation.clone(),
+            attributes,
+            weights,
+        }
+    }
+
+    /// Rows of self followed by the rows of other, e.g. putting two folds back together.
+    /// Both need the same attributes, see check_header
+    pub fn concat(&self, other: &DataSet) -> Result<Self, HeaderError> {
+        self.check_header(other)?;
+        let attributes = self
+            .attributes
+            .iter()
+            .zip(&other.attributes)
+            .map(|(attribute, other)| {
+                let mut data = attribute.data.clone();
+                (0..other.data.len()).for_each(|row| data.push_from(&other.data, row));
+                Attribute {
+                    label: attribute.label.clone(),
+                    data,
+                }
+            })
+            .collect();
+        let weights = match self.is_weighted() || other.is_weighted() {
+            true => (0..self.get_data_len())
+                .map(|row| self.get_weight(row))
+                .chain((0..other.get_data_len()).map(|row| other.get_weight(row)))
+                .collect(),
+            false => Vec::new(),
+        };
+        Ok(Self {
+            relation: self.relation.clone(),
+            attributes,
+            weights,
+        })
+    }
+
+    fn resolve_all<T: Into<Target>>(
+        &self,
+        attributes: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<usize>, TargetError> {
+        attributes
+            .into_iter()
+            .map(|attribute| attribute.into().resolve(self))
+            .collect()
+    }
+}
+
+impl Data {
+    // Appends entry index of other, which has to be the same type of column
+    pub(super) fn push_from(&mut self, other: &Data, index: usize) {
+        match (self, other) {
+            (
+                Data::Numeric(data) | Data::Real(data) | Data::Integer(data),
+                Data::Numeric(other) | Data::Real(other) | Data::Integer(other),
+            ) => match other.get(index) {
+                Some(value) => data.push(value),
+                None => data.push_missing(),
+            },
+            (Data::Nominal(nominal), Data::Nominal(other)) => nominal.push_id(other.get_id(index)),
+            (Data::String(data), Data::String(other)) => data.push(other[index].clone()),
+            (Data::Date(dates), Data::Date(other)) => match other.get(index) {
+                Some(date) => dates.push(date),
+                None => dates.push_missing(),
+            },
+            (Data::Relational(bags), Data::Relational(other)) => {
+                bags.push_bag(other.get(index).cloned())
+            }
+            _ => unreachable!("Columns need to be the same type"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::{DataEntry, Value};
+
+    #[test]
+    fn test_select_drop_filter() {
+        let data_set = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let selected = data_set
+            .select_attributes(["ph", "aluminum", "type"])
+            .unwrap();
+        assert_eq!(selected.get_len(), 3);
+        assert_eq!(selected.get_attributes()[0].label, "ph");
+        assert_eq!(selected.get_value(1, 5), data_set.get_value(0, 5));
+
+        let dropped = data_set.drop_attributes([Target::Last, 0.into()]).unwrap();
+        assert_eq!(dropped.get_len(), data_set.get_len() - 2);
+        assert_eq!(dropped.get_attributes()[0].label, "calcium");
+        let error = data_set.select_attributes(["colour"]).unwrap_err();
+        assert_eq!(error.to_string(), "No attribute is named 'colour'");
+        let error = data_set.drop_attributes([40]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Attribute index 40 is out of range, there are 19 attributes"
+        );
+
+        let lakes = data_set.filter(|row| {
+            matches!(
+                row.get_by_name("type"),
+                Some(Value::Nominal { label: "lake", .. })
+            )
+        });
+        assert!(lakes.get_data_len() > 0);
+        assert!(lakes.get_data_len() < data_set.get_data_len());
+        assert!(lakes
+            .rows()
+            .all(|row| row.get_entry(8) == DataEntry::Nominal(0)));
+    }
+
+    #[test]
+    fn test_concat() {
+        let fold1 = DataSet::import("./test_data/lakesDiscreteFold1.arff").unwrap();
+        let fold2 = DataSet::import("./test_data/lakesDiscreteFold2.arff").unwrap();
+        let both = fold1.concat(&fold2).unwrap();
+        assert_eq!(
+            both.get_data_len(),
+            fold1.get_data_len() + fold2.get_data_len()
+        );
+        let last = both.get_data_len() - 1;
+        assert_eq!(
+            both.get_value(3, last),
+            fold2.get_value(3, fold2.get_data_len() - 1)
+        );
+        // Taking the rows back out gives the second fold again
+        let rows: Vec<_> = (fold1.get_data_len()..both.get_data_len()).collect();
+        assert_eq!(
+            both.take_rows(&rows).get_attributes(),
+            fold2.get_attributes()
+        );
+
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert!(matches!(
+            fold1.concat(&lakes),
+            Err(HeaderError::Type { .. })
+        ));
+        let dropped = fold2.drop_attributes(["aluminum"]).unwrap();
+        assert!(matches!(
+            fold1.concat(&dropped),
+            Err(HeaderError::AttributeCount { .. })
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use itertools::Itertools;
+
+use super::{Attribute, Data, DataSet};
+
+impl DataSet {
+    /// Writes the data set to file_path as ARFF.
+    /// DataSet::import on the written file gives back an equal DataSet
+    pub fn export(&self, file_path: &str) -> io::Result<()> {
+        // Lots of small writes so buffering makes a big difference
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write_arff(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the data set as ARFF to anything that implements Write (files, Vec<u8>, stdout, ...).
+    /// Rows are written in sparse format if any numeric column is stored sparsely
+    pub fn write_arff<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "@relation {}", quote(&self.relation))?;
+        writeln!(writer)?;
+        for attribute in &self.attributes {
+            write_declaration(&mut writer, attribute, "")?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "@data")?;
+
+        let sparse = self.attributes.iter().any(|attribute| {
+            matches!(&attribute.data, Data::Numeric(data) | Data::Real(data) | Data::Integer(data) if data.is_sparse())
+        });
+        for row in 0..self.get_data_len() {
+            writeln!(writer, "{}", self.row_text(row, sparse))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn row_text(&self, row: usize, sparse: bool) -> String {
+        let text = match sparse {
+            true => format!(
+                "{{{}}}",
+                self.attributes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, attribute)| !is_default(attribute, row))
+                    .map(|(column, attribute)| format!("{} {}", column, value(attribute, row)))
+                    .join(",")
+            ),
+            false => self
+                .attributes
+                .iter()
+                .map(|attribute| value(attribute, row))
+                .join(","),
+        };
+        match self.get_weight(row) {
+            weight if weight != 1.0 => format!("{},{{{}}}", text, weight),
+            _ => text,
+        }
+    }
+}
+
+// Every row of a bag on its own line, quote() turns the line breaks into \n
+pub(super) fn bag_text(bag: &DataSet) -> String {
+    (0..bag.get_data_len())
+        .map(|row| bag.row_text(row, false))
+        .join("\n")
+}
+
+// Relational attributes are followed by their nested attributes and an @end line
+fn write_declaration<W: Write>(
+    writer: &mut W,
+    attribute: &Attribute,
+    indent: &str,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{}@attribute {} {}",
+        indent,
+        quote(&attribute.label),
+        declaration(attribute)
+    )?;
+    if let Data::Relational(bags) = &attribute.data {
+        for nested in bags.get_header().get_attributes() {
+            write_declaration(writer, nested, &format!("{}  ", indent))?;
+        }
+        writeln!(writer, "{}@end {}", indent, quote(&attribute.label))?;
+    }
+    Ok(())
+}
+
+// Type part of an @attribute line
+fn declaration(attribute: &Attribute) -> String {
+    match &attribute.data {
+        Data::Numeric(_) => "numeric".to_owned(),
+        Data::Real(_) => "real".to_owned(),
+        Data::Integer(_) => "integer".to_owned(),
+        Data::Nominal(nominal) => format!(
+            "{{{}}}",
+            nominal
+                .get_fields()
+                .iter()
+                .map(|field| quote(field))
+                .join(",")
+        ),
+        Data::String(_) => "string".to_owned(),
+        Data::Date(dates) => format!("date {}", quote(dates.get_format().pattern())),
+        Data::Relational(_) => "relational".to_owned(),
+    }
+}
+
+fn value(attribute: &Attribute, row: usize) -> Cow<'_, str> {
+    let value = match &attribute.data {
+        // Display for floats gives the shortest text that parses back to the same float
+        Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => {
+            data.get(row).map(|value| value.to_string().into())
+        }
+        Data::Nominal(nominal) => nominal.get(row).map(quote),
+        Data::String(data) => data[row].as_deref().map(quote),
+        Data::Date(dates) => dates
+            .get(row)
+            .map(|date| quote(&dates.get_format().format(&date)).into_owned().into()),
+        Data::Relational(bags) => bags
+            .get(row)
+            .map(|bag| quote(&bag_text(bag)).into_owned().into()),
+    };
+    value.unwrap_or("?".into())
+}
+
+// Whether a sparse row can leave the value out, see Attribute::push_default
+fn is_default(attribute: &Attribute, row: usize) -> bool {
+    match &attribute.data {
+        Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => data.get(row) == Some(0.0),
+        Data::Nominal(nominal) => nominal.get_id(row) == Some(0),
+        Data::String(data) => data[row].as_deref() == Some(""),
+        Data::Date(dates) => dates.get(row) == Some(Default::default()),
+        Data::Relational(bags) => bags.get(row).is_some_and(|bag| bag.get_data_len() == 0),
+    }
+}
+
+// Quotes text if it would otherwise be split up or read as a missing value
+// Double quotes are used when that saves escaping single quotes, see Parser::parse_quoted
+fn quote(text: &str) -> Cow<'_, str> {
+    let needs_quotes = text.is_empty()
+        || text == "?"
+        || text.contains(|c: char| c.is_whitespace() || ",{}'\"%\\".contains(c));
+    if !needs_quotes {
+        return Cow::Borrowed(text);
+    }
+    let quote = match text.contains('\'') && !text.contains('"') {
+        true => '"',
+        false => '\'',
+    };
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push(quote);
+    text.chars().for_each(|c| match c {
+        '\n' => quoted.push_str("\\n"),
+        '\r' => quoted.push_str("\\r"),
+        '\t' => quoted.push_str("\\t"),
+        '\\' => quoted.push_str("\\\\"),
+        c if c == quote => {
+            quoted.push('\\');
+            quoted.push(c)
+        }
+        c => quoted.push(c),
+    });
+    quoted.push(quote);
+    Cow::Owned(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data_set: &DataSet) -> DataSet {
+        let mut bytes = Vec::new();
+        data_set.write_arff(&mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_test_data() {
+        std::fs::read_dir("./test_data")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "arff")
+            })
+            .for_each(|path| {
+                let data_set = DataSet::import(path.to_str().unwrap()).unwrap();
+                assert_eq!(round_trip(&data_set), data_set, "{:?}", path);
+            });
+    }
+
+    #[test]
+    fn test_round_trip_every_type() {
+        let data_set: DataSet = "@relation 'every type'
+            @attribute 'a number' numeric
+            @attribute r real
+            @attribute colour {red, 'light blue', \"it's\", 'New York, NY'}
+            @attribute name string
+            @attribute when date 'dd/MM/yyyy HH:mm'
+            @data
+            1.5,-2,'light blue','hello world','03/04/2001 12:12'
+            ?,0.000001,\"it's\",?,?
+            7,1,'New York, NY','both \\'\" \\\\ and\\ttab',? % comment
+            {1 3, 3 ''}"
+            .parse()
+            .unwrap();
+        assert_eq!(round_trip(&data_set), data_set);
+
+        let mut bytes = Vec::new();
+        data_set.write_arff(&mut bytes).unwrap();
+        let sparse = DataSet::parse_arff(&String::from_utf8(bytes).unwrap(), true).unwrap();
+        assert_eq!(round_trip(&sparse), data_set);
+    }
+}
@@ -2,18 +2,32 @@ use std::collections::HashMap;
 
 use crate::{
     all_equal,
-    data_set::{Data, DataSet, Nominal},
-    majority_vote_ordered, normalize, swap_remove,
+    data_set::{
+        Binning, Data, DataSet, Discretizer, HeaderError, Nominal, Numeric, Target, TargetError,
+    },
+    swap_remove,
     test_statistics::ConfusionMatrix,
+    weighted_majority_vote_ordered,
 };
 
+/// Stands in for a missing value in ProcessedData.
+/// Missing values are skipped when counting entropy, follow the largest branch while training,
+/// and stop at the majority value of the node that splits on them when querying
+pub const MISSING: u32 = u32::MAX;
+
 // Just need IDs for the categories. Could considering using u16 or u8s instead
 // layout is used to convert test data
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProcessedData {
     pub layout: Vec<DataType>,
     // OOD structure: [[1234],[1234],...]
     // DOD structure: [[1111],[2222], ...]
     data: Vec<Vec<u32>>,
+    // Instance weights, used for entropy and the majority votes
+    weights: Vec<f32>,
+    // Attributes without any rows so the target can be picked by name
+    // and test sets can be checked against the training set
+    header: DataSet,
 }
 
 impl ProcessedData {
@@ -29,6 +43,17 @@ impl ProcessedData {
         &self.data[index]
     }
 
+    // (target value, weight) for every index, what the majority votes count
+    fn target_votes<'a>(
+        &'a self,
+        target: usize,
+        indices: &'a [usize],
+    ) -> impl Iterator<Item = (u32, f32)> + 'a {
+        indices
+            .iter()
+            .map(move |index| (self.data[target][*index], self.weights[*index]))
+    }
+
     // Returning error here so I could hunt down problem area
     fn get_value(&self, attribute: usize, index: usize) -> Result<u32, &str> {
         // Question mark operator is short hand for returning the error
@@ -39,19 +64,44 @@ impl ProcessedData {
 }
 
 impl ProcessedData {
+    /// Numeric attributes are split into sqrt(rows) intervals of the same width
     pub fn import(data_set: &DataSet) -> Self {
+        let buckets = (data_set.get_data_len() as f32).sqrt() as usize;
+        Self::import_with(data_set, Binning::EqualWidth(buckets))
+    }
+
+    /// Numeric attributes are split into intervals picked by binning.
+    /// For supervised discretization apply a Discretizer to data_set first and import the result
+    pub fn import_with(data_set: &DataSet, binning: Binning) -> Self {
+        let discretizer = Discretizer::unsupervised(data_set, binning);
         let (layout, data): (Vec<_>, Vec<_>) = data_set
             .get_attributes()
             .iter()
-            .map(|attribute| match attribute.get_data() {
+            .enumerate()
+            .map(|(column, attribute)| match attribute.get_data() {
                 Data::Nominal(data) => Self::process_nominal(data),
-                Data::Numeric(data) | Data::Real(data) => Self::process_numeric(data),
-                Data::Date(_) => panic!("Teach me how to process date data"),
+                Data::Numeric(_) | Data::Real(_) | Data::Integer(_) | Data::Date(_) => {
+                    // Every numeric attribute gets cut points
+                    Self::process_numeric(
+                        &attribute.as_numeric().unwrap(),
+                        discretizer.cut_points(column).unwrap(),
+                    )
+                }
                 Data::String(_) => panic!("Teach me how to process strings"),
-                Data::NotImplemented(data_type) => panic!("{} needs to be implemented!", data_type),
+                Data::Relational(_) => {
+                    panic!("Summarize bags with multi_instance::summarize before training")
+                }
             })
             .unzip();
-        Self { layout, data }
+        let weights = (0..data_set.get_data_len())
+            .map(|index| data_set.get_weight(index))
+            .collect();
+        Self {
+            layout,
+            data,
+            weights,
+            header: data_set.header(),
+        }
     }
 
     // Test data needs to be placed into same buckets as training data so needs it's layout
@@ -62,9 +112,10 @@ impl ProcessedData {
             .zip(layout.iter())
             .map(|(attribute, layout)| match attribute.get_data() {
                 Data::Nominal(data) => Self::process_nominal(data).1,
-                Data::Numeric(data) | Data::Real(data) => {
+                Data::Numeric(data) | Data::Real(data) | Data::Integer(data) => {
                     Self::process_numeric_from_layout(data, layout)
                 }
+                Data::Date(dates) => Self::process_numeric_from_layout(&dates.to_numeric(), layout),
                 _ => panic!("Haven't implemented other types yet!"),
             })
             .collect();
@@ -73,10 +124,22 @@ impl ProcessedData {
             data,
             // Could optimize this out but compiler probably doing so already
             layout: layout.to_vec(),
+            weights: (0..data_set.get_data_len())
+                .map(|index| data_set.get_weight(index))
+                .collect(),
+            header: data_set.header(),
         }
     }
 
-    fn process_numeric_from_layout(data: &[f32], layout: &DataType) -> Vec<u32> {
+    /// Same as import_test_data(data_set, &self.layout) but first checks data_set
+    /// has the same attributes as the data self was imported from, see DataSet::check_header.
+    /// DataSet::align_to can fix up test sets that declare things in another order
+    pub fn import_test_set(&self, data_set: &DataSet) -> Result<Self, HeaderError> {
+        self.header.check_header(data_set)?;
+        Ok(Self::import_test_data(data_set, &self.layout))
+    }
+
+    fn process_numeric_from_layout(data: &Numeric, layout: &DataType) -> Vec<u32> {
         let parser = match layout {
             DataType::Numeric(numeric_type) => numeric_type,
             // unreachable!() panics when reached
@@ -84,33 +147,29 @@ impl ProcessedData {
             // but requires unsafe rust to do so and causes undefined behavior if reached
             _ => unreachable!(),
         };
-        data.iter().map(|x| parser.convert(*x)).collect()
+        data.iter()
+            .map(|x| x.map_or(MISSING, |x| parser.convert(x)))
+            .collect()
     }
 
     fn process_nominal(data: &Nominal) -> (DataType, Vec<u32>) {
         let layout = DataType::new_nominal(data.get_map());
         (
             layout,
-            data.get_data().iter().map(|value| *value as u32).collect(),
+            data.iter()
+                .map(|value| value.map_or(MISSING, u32::from))
+                .collect(),
         )
     }
 
-    fn process_numeric(data: &[f32]) -> (DataType, Vec<u32>) {
-        let buckets = (data.len() as f32).sqrt() as u32;
-
-        let min = data
-            .iter()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(&0.0)
-            .to_owned();
-        let max = data
+    fn process_numeric(data: &Numeric, cut_points: &[f64]) -> (DataType, Vec<u32>) {
+        let data_type = NumericType {
+            cut_points: cut_points.to_vec(),
+        };
+        let data = data
             .iter()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(&0.0)
-            .to_owned();
-
-        let data_type = NumericType { min, max, buckets };
-        let data = data.iter().map(|value| data_type.convert(*value)).collect();
+            .map(|value| value.map_or(MISSING, |value| data_type.convert(value)))
+            .collect();
         let layout = DataType::Numeric(data_type);
 
         (layout, data)
@@ -118,121 +177,197 @@ impl ProcessedData {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataType {
     Numeric(NumericType),
     Nominal(NominalType),
 }
 
 impl DataType {
-    fn new_nominal(map: &HashMap<String, u8>) -> Self {
+    fn new_nominal(map: &HashMap<String, u32>) -> Self {
         Self::Nominal(NominalType { map: map.clone() })
     }
+
+    // Number of ids values can take, not counting MISSING
+    fn value_count(&self) -> usize {
+        match self {
+            Self::Numeric(numeric) => numeric.cut_points.len() + 1,
+            Self::Nominal(nominal) => nominal.map.len(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NumericType {
-    min: f32,
-    max: f32,
-    buckets: u32,
+    // Same intervals as a Discretizer: bucket i is (cut_points[i - 1], cut_points[i]]
+    cut_points: Vec<f64>,
 }
 
 impl NumericType {
     fn convert(&self, data: f32) -> u32 {
-        let value = (normalize(self.min, self.max, data) * self.buckets as f32) as i64;
-        if value >= self.buckets as i64 {
-            self.buckets - 1
-        } else if value < 0 {
-            0
-        } else {
-            value as u32
-        }
+        self.cut_points
+            .partition_point(|cut_point| *cut_point < data as f64) as u32
     }
 }
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NominalType {
-    map: HashMap<String, u8>,
+    map: HashMap<String, u32>,
 }
 
 impl NominalType {
     pub fn convert(&self, value: &str) -> u32 {
-        *self.map.get(value).expect("Key not in map") as u32
+        *self.map.get(value).expect("Key not in map")
     }
 }
 
-// (split value) -> (target value) -> (count of target value for split value)
+// (split value) -> (target value) -> (total weight of target value for split value)
+// Counts are weighted so an instance with weight 2 counts the same as two copies of it
 struct Counter {
-    counts: HashMap<u32, HashMap<u32, u32>>,
-    len: usize,
+    counts: HashMap<u32, HashMap<u32, f32>>,
+    len: f32,
+    // Weight of the pairs with a target value but no split value
+    missing_len: f32,
 }
 
 impl Counter {
     fn new() -> Self {
         Self {
             counts: HashMap::new(),
-            len: 0,
+            len: 0.0,
+            missing_len: 0.0,
         }
     }
 
+    #[cfg(test)]
     fn insert(&mut self, split_value: u32, target_value: u32) {
-        self.len += 1;
-        self.counts
+        self.insert_weighted(split_value, target_value, 1.0)
+    }
+
+    // Pairs with a missing value don't count towards the entropy,
+    // a missing split value still counts against the gain
+    fn insert_weighted(&mut self, split_value: u32, target_value: u32, weight: f32) {
+        if target_value == MISSING {
+            return;
+        }
+        if split_value == MISSING {
+            self.missing_len += weight;
+            return;
+        }
+        self.len += weight;
+        *self
+            .counts
             .entry(split_value)
-            .and_modify(|target_count| {
-                target_count
-                    .entry(target_value)
-                    .and_modify(|count| *count += 1)
-                    .or_insert(1);
-            })
-            .or_insert(HashMap::from([(target_value, 1u32)]));
+            .or_default()
+            .entry(target_value)
+            .or_insert(0.0) += weight;
     }
 
     fn entropy(&self) -> f32 {
         self.counts
             .values()
             .map(|target_count| {
-                let partition_size = target_count.values().sum::<u32>();
-                let partition_entropy = target_count
-                    .values()
-                    .map(|count| *count as f32 / partition_size as f32)
-                    .map(|probability| -probability * probability.log2())
-                    .sum::<f32>();
-                (partition_size as f32 / self.len as f32) * partition_entropy
+                let partition_size = target_count.values().sum::<f32>();
+                match partition_size > 0.0 {
+                    true => (partition_size / self.len) * entropy(target_count.values()),
+                    false => 0.0,
+                }
             })
             .sum()
     }
+
+    // Information gain of the rows with a split value scaled by the fraction of
+    // the weight they make up, like C4.5, so mostly missing attributes aren't favoured
+    fn gain(&self) -> f32 {
+        if self.len <= 0.0 {
+            return 0.0;
+        }
+        let mut target_counts: HashMap<u32, f32> = HashMap::new();
+        self.counts
+            .values()
+            .flatten()
+            .for_each(|(target_value, count)| {
+                *target_counts.entry(*target_value).or_insert(0.0) += count
+            });
+        let known = self.len / (self.len + self.missing_len);
+        known * (entropy(target_counts.values()) - self.entropy())
+    }
 }
 
+// Entropy of a distribution given as weights
+fn entropy<'a>(counts: impl Iterator<Item = &'a f32> + Clone) -> f32 {
+    let total = counts.clone().sum::<f32>();
+    counts
+        // Zero weight counts would give 0 * log(0) = NaN
+        .filter(|count| **count > 0.0)
+        .map(|count| count / total)
+        .map(|probability| -probability * probability.log2())
+        .sum::<f32>()
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ID3 {
     root: Node,
+    // Column of the target, set by train
+    target: usize,
+    // Number of attributes and target values of the training data, set by train
+    attribute_len: usize,
+    classes: usize,
 }
 
 impl ID3 {
     pub fn new() -> Self {
         let root = Node::Internal(InternalNode::new());
-        Self { root }
+        Self {
+            root,
+            target: 0,
+            attribute_len: 0,
+            classes: 0,
+        }
     }
 
-    pub fn train(&mut self, data: &ProcessedData, target: usize) {
+    /// Instances with a missing target value are left out of training,
+    /// so at least one instance needs a target value.
+    /// target can be an index, a label, "first" or "last"
+    pub fn train(
+        &mut self,
+        data: &ProcessedData,
+        target: impl Into<Target>,
+    ) -> Result<(), TargetError> {
+        // Numeric targets are binned like every other attribute so any type will do
+        let target = target.into().resolve(&data.header)?;
+        self.target = target;
         let attribute_len = data.attribute_len();
+        self.attribute_len = attribute_len;
+        self.classes = data.layout[target].value_count();
         let data_len = data.data_len();
-        let majority_element =
-            majority_vote_ordered((0..data_len).map(|index| data.get_value(target, index)))
-                .unwrap();
+        let indices: Vec<_> = (0..data_len)
+            .filter(|index| data.get_value(target, *index) != Ok(MISSING))
+            .collect();
+        if indices.is_empty() {
+            return Err(TargetError::NoKnownValues {
+                attribute: data.header.get_attributes()[target].label.clone(),
+            });
+        }
+        let majority_element = weighted_majority_vote_ordered(data.target_votes(target, &indices));
         match &mut self.root {
             Node::Internal(node) => node.value = majority_element,
             _ => unreachable!(),
         }
-        let indices: Vec<_> = (0..data_len).collect();
         let mut attributes: Vec<_> = (0..attribute_len)
             .filter(|index| *index != target)
             .collect();
         self.root.train(&indices, data, &mut attributes, target);
+        Ok(())
     }
 
-    pub fn test(&self, test_data: &ProcessedData, target: usize) {
-        // Hackish O(n) hack to get order of target feature
-        let size = *test_data.get_attribute(target).iter().max().unwrap() as usize + 1;
-        let count = (0..test_data.data_len())
+    /// Instances with a missing target value can't be scored so they're skipped.
+    /// Uses the target that was trained on
+    pub fn test(&self, test_data: &ProcessedData) {
+        let target = self.target;
+        let scored: Vec<_> = (0..test_data.data_len())
+            .filter(|data_index| test_data.get_value(target, *data_index) != Ok(MISSING))
             .map(|data_index| {
                 (0..test_data.attribute_len())
                     .map(|attribute_index| {
@@ -241,13 +376,26 @@ impl ID3 {
                     .collect()
             })
             .map(|entry: Vec<u32>| (self.query(&entry), entry[target]))
-            .fold(
-                ConfusionMatrix::new(size),
-                |mut count, (predicted, actual)| {
-                    count.add_prediction(predicted as usize, actual as usize);
-                    count
-                },
-            );
+            .collect();
+        // Hackish O(n) hack to get order of target feature
+        let size = match scored
+            .iter()
+            .map(|(predicted, actual)| predicted.max(actual))
+            .max()
+        {
+            Some(max) => *max as usize + 1,
+            None => {
+                println!("Nothing to score, every instance is missing the target");
+                return;
+            }
+        };
+        let count = scored.iter().fold(
+            ConfusionMatrix::new(size),
+            |mut count, (predicted, actual)| {
+                count.add_prediction(*predicted as usize, *actual as usize);
+                count
+            },
+        );
 
         count.display(1);
     }
@@ -261,6 +409,59 @@ impl ID3 {
     }
 }
 
+// Checks for models read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl ProcessedData {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let attributes = self.header.get_attributes();
+        if self.layout.len() != attributes.len() || self.data.len() != attributes.len() {
+            return Err(format!(
+                "ProcessedData has {} layouts and {} columns but there are {} attributes",
+                self.layout.len(),
+                self.data.len(),
+                attributes.len()
+            ));
+        }
+        attributes
+            .iter()
+            .zip(&self.layout)
+            .zip(&self.data)
+            .try_for_each(|((attribute, layout), column)| {
+                let matches = match (attribute.get_data(), layout) {
+                    (Data::Nominal(nominal), DataType::Nominal(_)) => {
+                        nominal.size() == layout.value_count()
+                    }
+                    (Data::Nominal(_), _) | (_, DataType::Nominal(_)) => false,
+                    _ => attribute.as_numeric().is_some(),
+                };
+                let values = column
+                    .iter()
+                    .all(|value| *value == MISSING || (*value as usize) < layout.value_count());
+                match matches && values && column.len() == self.weights.len() {
+                    true => Ok(()),
+                    false => Err(format!(
+                        "ProcessedData's values for attribute '{}' don't match its layout",
+                        attribute.label
+                    )),
+                }
+            })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ID3 {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let untrained = self.attribute_len == 0
+            && matches!(&self.root, Node::Internal(node) if node.children.is_empty());
+        let trained =
+            self.target < self.attribute_len && self.root.check(self.attribute_len, self.classes);
+        match untrained || trained {
+            true => Ok(()),
+            false => Err("ID3 has nodes that don't match its attributes".to_owned()),
+        }
+    }
+}
+
 impl Default for ID3 {
     fn default() -> Self {
         Self::new()
@@ -268,6 +469,7 @@ impl Default for ID3 {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Node {
     Internal(InternalNode),
     Leaf(LeafNode),
@@ -294,6 +496,22 @@ impl Node {
         }
     }
 
+    // Every split is on one of the attributes and every value is a target value
+    #[cfg(feature = "serde")]
+    fn check(&self, attribute_len: usize, classes: usize) -> bool {
+        match self {
+            Self::Internal(node) => {
+                node.category < attribute_len
+                    && (node.value as usize) < classes
+                    && node
+                        .children
+                        .iter()
+                        .all(|child| child.check(attribute_len, classes))
+            }
+            Self::Leaf(node) => (node.value as usize) < classes,
+        }
+    }
+
     fn query(&self, data: &[u32]) -> u32 {
         match self {
             Self::Internal(node) => node.query(data),
@@ -312,6 +530,7 @@ impl Node {
 // Category is the category the children got split on
 // Key is the category value of the parents category
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct InternalNode {
     category: usize,
     key: u32,
@@ -367,9 +586,9 @@ impl InternalNode {
         attributes: &mut [usize],
         target: usize,
     ) {
-        // attribute: The index into the data array with least entropy
+        // attribute: The index into the data array with the most information gain
         // attribute_index: Location of attribute in the attribute-array
-        let (&attribute, attribute_index, _entropy) = attributes
+        let (&attribute, attribute_index, _gain) = attributes
             .iter()
             .enumerate()
             .map(|(attribute_index, attribute)| {
@@ -377,29 +596,48 @@ impl InternalNode {
                 data.get_attribute(*attribute)
                     .iter()
                     .zip(data.get_attribute(target).iter())
-                    .for_each(|(split_value, target_value)| {
-                        counter.insert(*split_value, *target_value)
+                    .zip(data.weights.iter())
+                    .for_each(|((split_value, target_value), weight)| {
+                        counter.insert_weighted(*split_value, *target_value, *weight)
                     });
 
-                (attribute, attribute_index, counter.entropy())
+                (attribute, attribute_index, counter.gain())
             })
-            .min_by(|a, b| a.2.partial_cmp(&b.2).expect("Can't compare NaN's!"))
+            // min_by keeps the first attribute when gains are tied
+            .min_by(|a, b| b.2.partial_cmp(&a.2).expect("Can't compare NaN's!"))
             .unwrap();
 
         self.category = attribute;
 
         // Create the arrays for the children to learn from
         let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+        let mut missing = Vec::new();
         indices
             .iter()
             .map(|index| (index, data.get_value(attribute, *index).unwrap()))
-            .for_each(|(index, attribute_value)| {
-                children
-                    .entry(attribute_value)
-                    .and_modify(|indices| indices.push(*index))
-                    .or_insert(vec![*index]);
+            .for_each(|(index, attribute_value)| match attribute_value {
+                MISSING => missing.push(*index),
+                _ => {
+                    children
+                        .entry(attribute_value)
+                        .and_modify(|indices| indices.push(*index))
+                        .or_insert(vec![*index]);
+                }
             });
 
+        // Instances missing the split value follow the largest branch
+        // Ties are broken by key so training stays deterministic
+        match children
+            .iter_mut()
+            .max_by_key(|(key, indices)| (indices.len(), std::cmp::Reverse(**key)))
+        {
+            Some((_, indices)) => indices.append(&mut missing),
+            None if !missing.is_empty() => {
+                children.insert(MISSING, missing);
+            }
+            None => (),
+        }
+
         // Remove the attribute just consumed by this call
         let attributes = swap_remove(attributes, attribute_index);
 
@@ -411,11 +649,7 @@ impl InternalNode {
                 if attributes.is_empty() {
                     Node::Leaf(LeafNode {
                         key: attribute,
-                        value: majority_vote_ordered(
-                            indices
-                                .iter()
-                                .map(|index| data.get_value(target, *index).unwrap()),
-                        ),
+                        value: weighted_majority_vote_ordered(data.target_votes(target, indices)),
                     })
                 }
                 // If all target values are the same then return that target value
@@ -429,11 +663,7 @@ impl InternalNode {
                 else {
                     let mut child = InternalNode::from_key_value(
                         attribute,
-                        majority_vote_ordered(
-                            indices
-                                .iter()
-                                .map(|index| data.get_value(target, *index).unwrap()),
-                        ),
+                        weighted_majority_vote_ordered(data.target_votes(target, indices)),
                     );
                     child.train(indices, data, attributes, target);
                     Node::Internal(child)
@@ -444,6 +674,7 @@ impl InternalNode {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct LeafNode {
     key: u32,
     value: u32,
@@ -498,6 +729,85 @@ mod tests {
         counter.insert(1, 3);
 
         let result = counter.entropy();
-        assert!((result - 1.58496250072).abs() <= f32::EPSILON);
+        assert!((result - 1.584_962_5).abs() <= f32::EPSILON);
+    }
+
+    #[test]
+    fn test_entropy_weighted_matches_copies() {
+        let mut weighted = Counter::new();
+        weighted.insert_weighted(1, 1, 2.0);
+        weighted.insert_weighted(1, 2, 1.0);
+        weighted.insert_weighted(2, 2, 0.0);
+
+        let mut copies = Counter::new();
+        copies.insert(1, 1);
+        copies.insert(1, 1);
+        copies.insert(1, 2);
+
+        assert!((weighted.entropy() - copies.entropy()).abs() <= f32::EPSILON);
+    }
+
+    #[test]
+    fn test_gain_scaled_by_known_weight() {
+        // Splits the target perfectly on the rows it has a value for
+        let mut complete = Counter::new();
+        complete.insert(1, 1);
+        complete.insert(2, 2);
+        let mut mostly_missing = Counter::new();
+        mostly_missing.insert(1, 1);
+        mostly_missing.insert(2, 2);
+        (0..8).for_each(|_| mostly_missing.insert(MISSING, 1));
+        mostly_missing.insert(MISSING, MISSING);
+
+        assert_eq!(complete.entropy(), mostly_missing.entropy());
+        assert_eq!(complete.gain(), 1.0);
+        assert!((mostly_missing.gain() - 0.2).abs() <= f32::EPSILON);
+    }
+
+    #[test]
+    fn test_unknown_target() {
+        let header = "@relation test
+            @attribute colour {red, blue}
+            @attribute class {a, b}
+            @data";
+        let known: DataSet = format!("{}\nred,a\nblue,b", header).parse().unwrap();
+        let unknown: DataSet = format!("{}\nred,?\nblue,?", header).parse().unwrap();
+
+        let mut id3 = ID3::new();
+        assert_eq!(
+            id3.train(&ProcessedData::import(&unknown), "class"),
+            Err(TargetError::NoKnownValues {
+                attribute: "class".to_owned()
+            })
+        );
+        // A test set without a single target value has nothing to score
+        let processed = ProcessedData::import(&known);
+        id3.train(&processed, "class").unwrap();
+        id3.test(&processed.import_test_set(&unknown).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_file_checks() {
+        use crate::model_file::{tests::round_trip, Model, ModelError};
+        let data_set = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let processed = ProcessedData::import(&data_set);
+        let mut id3 = ID3::new();
+        round_trip(&id3);
+        id3.train(&processed, "ph").unwrap();
+        round_trip(&id3);
+        round_trip(&processed);
+
+        // Splitting on an attribute past the end of the rows would index out of bounds
+        match &mut id3.root {
+            Node::Internal(node) => node.category = processed.attribute_len(),
+            Node::Leaf(_) => unreachable!(),
+        }
+        let mut bytes = Vec::new();
+        id3.write_to(&mut bytes).unwrap();
+        assert!(matches!(
+            ID3::read_from(bytes.as_slice()),
+            Err(ModelError::Format(_))
+        ));
     }
 }
@@ -1,86 +1,91 @@
 use crate::{
-    data_set::{Data, DataEntry, DataSet},
-    majority_vote,
+    data_set::{Data, DataEntry, DataSet, HeaderError, Target, TargetError},
     test_statistics::ConfusionMatrix,
+    weighted_majority_vote,
 };
 
 /// K-Nearest Neighbors implementation. Works by taking a majority vote of the k-nearest neighbor's target value
+///
+/// Missing values are treated as being as far away as possible: a distance of 1 for nominal attributes
+/// and the squared range of the training values for numeric attributes.
+/// Training entries with a missing target value never get a vote and every other entry votes with its weight
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KNN {
     data: DataSet,
+    // Distance used for an attribute when either side is missing
+    missing_penalty: Vec<f32>,
+    // Column of the target
+    target: usize,
 }
 
 impl KNN {
-    /// Returns KNN instance that will use the data-points in data for queries
-    pub fn new(data: DataSet) -> Self {
-        Self { data }
+    /// Returns KNN instance that will use the data-points in data for queries.
+    /// Distances use the values as they are, so scale data (and the test sets) with a Scaler
+    /// first if attributes with big values like elevation shouldn't drown out the rest
+    /// target can be an index, a label, "first" or "last" and has to be nominal,
+    /// at least one entry needs a target value to vote with
+    pub fn new(data: DataSet, target: impl Into<Target>) -> Result<Self, TargetError> {
+        let target = target.into().resolve_nominal(&data)?;
+        if data.get_attributes()[target]
+            .assume_nominal()
+            .missing_count()
+            == data.get_data_len()
+        {
+            return Err(TargetError::NoKnownValues {
+                attribute: data.get_attributes()[target].label.clone(),
+            });
+        }
+        let missing_penalty = missing_penalties(&data);
+        Ok(Self {
+            data,
+            missing_penalty,
+            target,
+        })
     }
 
     // Returns a vector of indices into the dataset ordered by closest to the test point
-    fn nearest_neighbors(&self, test_set: &DataSet, index: usize, target: usize) -> Vec<usize> {
+    fn nearest_neighbors(&self, test_set: &DataSet, index: usize) -> Vec<usize> {
         let attribute_indices: Vec<usize> = (0..self.data.get_attributes().len())
-            .filter(|index| *index != target)
-            .collect();
-        let distances: Vec<Vec<f32>> = attribute_indices
-            .iter()
-            .map(
-                |attribute_index| match self.data.get_attributes()[*attribute_index].get_data() {
-                    Data::Numeric(data) => data
-                        .iter()
-                        .map(|lhs| {
-                            let rhs = match test_set.get_value(*attribute_index, index) {
-                                DataEntry::Numeric(data) => data,
-                                _ => unreachable!(),
-                            };
-                            distance_squared(*lhs, rhs)
-                        })
-                        .collect(),
-                    Data::Nominal(nominal) => nominal
-                        .get_data()
-                        .iter()
-                        .map(|lhs| {
-                            let rhs = match test_set.get_value(*attribute_index, index) {
-                                DataEntry::Nominal(data) => data,
-                                _ => unreachable!(),
-                            };
-                            distance_nominal(*lhs, rhs)
-                        })
-                        .collect(),
-                    _ => panic!("Need to implement more types!"),
-                },
-            )
+            .filter(|index| *index != self.target)
             .collect();
+        let distances = distances(
+            &self.data,
+            &self.missing_penalty,
+            &attribute_indices,
+            test_set,
+            index,
+        );
 
-        let distances: Vec<f32> = (0..self.data.get_data_len())
-            .map(|data_index| {
-                // Note that there is no need to square-root since it doesn't change ordering
-                (0..distances.len())
-                    .map(|attribute_index| distances[attribute_index][data_index])
-                    .sum::<f32>()
-            })
+        // Entries without a target value can't vote
+        let mut nearest_neighbors: Vec<usize> = (0..self.data.get_data_len())
+            .filter(|index| !self.data.get_value(self.target, *index).is_missing())
             .collect();
-
-        let mut nearest_neighbors: Vec<usize> = (0..self.data.get_data_len()).collect();
         nearest_neighbors.sort_by(|a, b| distances[*a].partial_cmp(&distances[*b]).unwrap());
         nearest_neighbors
     }
 
     // Returns predicted target value based on the k nearest neighbors
-    fn query_k(&self, nearest_neighbors: &[usize], k: usize, target: usize) -> DataEntry {
-        majority_vote(
-            nearest_neighbors
-                .iter()
-                .take(k)
-                .map(|index| self.data.get_value(target, *index)),
-        )
+    fn query_k(&self, nearest_neighbors: &[usize], k: usize) -> DataEntry {
+        weighted_majority_vote(nearest_neighbors.iter().take(k).map(|index| {
+            (
+                self.data.get_value(self.target, *index),
+                self.data.get_weight(*index),
+            )
+        }))
     }
 
     /// Runs an accuracy test for each value of k and displays it.
     /// We actually only need to calculate the distances once then query the
-    /// k nearest neighbors for each k using the same sorted nearest-neighbors vector
-    pub fn test(&self, test_set: &DataSet, target: usize) {
-        // Vector of nearest neighbors for each entry of the test data
+    /// k nearest neighbors for each k using the same sorted nearest-neighbors vector.
+    /// The test_set needs the same attributes as the training data (see DataSet::align_to).
+    /// Test entries with a missing target value are skipped
+    pub fn test(&self, test_set: &DataSet) -> Result<(), HeaderError> {
+        self.data.check_header(test_set)?;
+        let target = self.target;
+        // Vector of (test index, nearest neighbors) for each scorable entry of the test data
         let nearest_neighbors: Vec<_> = (0..test_set.get_data_len())
-            .map(|index| self.nearest_neighbors(test_set, index, target))
+            .filter(|index| !test_set.get_value(target, *index).is_missing())
+            .map(|index| (index, self.nearest_neighbors(test_set, index)))
             .collect();
 
         // Iterate over each possible value of k
@@ -89,11 +94,8 @@ impl KNN {
                 let n = test_set.get_attributes()[target].assume_nominal().size();
                 let count = nearest_neighbors
                     .iter()
-                    .enumerate()
                     // Worth noting that nearest_neighbors in this context is for the single entry, not all the entries
-                    .map(|(index, nearest_neighbors)| {
-                        (index, self.query_k(nearest_neighbors, k, target))
-                    })
+                    .map(|(index, nearest_neighbors)| (*index, self.query_k(nearest_neighbors, k)))
                     .map(|(index, prediction)| {
                         (
                             prediction.assume_nominal(),
@@ -110,13 +112,115 @@ impl KNN {
                 println!("k: {}", k);
                 count.display(1);
             });
+        Ok(())
     }
 }
 
+// Checks for models read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl KNN {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let attributes = self.data.get_attributes();
+        if !matches!(
+            attributes
+                .get(self.target)
+                .map(|attribute| attribute.get_data()),
+            Some(Data::Nominal(_))
+        ) {
+            return Err("KNN's target isn't a nominal attribute".to_owned());
+        }
+        // NaN distances can't be sorted
+        match self.missing_penalty.len() == attributes.len()
+            && self.missing_penalty.iter().all(|penalty| !penalty.is_nan())
+        {
+            true => Ok(()),
+            false => Err(format!(
+                "KNN has {} missing penalties but there are {} attributes",
+                self.missing_penalty.len(),
+                attributes.len()
+            )),
+        }
+    }
+}
+
+// Distance used for each attribute of data when either side is missing:
+// the squared range of the values for numeric attributes and 1 for everything else
+pub(crate) fn missing_penalties(data: &DataSet) -> Vec<f32> {
+    data.get_attributes()
+        .iter()
+        .map(|attribute| match attribute.as_numeric() {
+            Some(data) => {
+                let (min, max) = data
+                    .iter()
+                    .flatten()
+                    .fold((f32::MAX, f32::MIN), |(min, max), value| {
+                        (min.min(value), max.max(value))
+                    });
+                // min > max only when every value is missing
+                match min > max {
+                    true => 0.0,
+                    false => distance_squared(min, max),
+                }
+            }
+            None => 1.0,
+        })
+        .collect()
+}
+
+// Squared distance from row index of other to every row of data over the given attributes,
+// other has to have the same attributes as data
+pub(crate) fn distances(
+    data: &DataSet,
+    missing_penalty: &[f32],
+    attributes: &[usize],
+    other: &DataSet,
+    index: usize,
+) -> Vec<f32> {
+    let distances: Vec<Vec<f32>> = attributes
+        .iter()
+        .map(|attribute_index| {
+            let rhs = other.get_value(*attribute_index, index);
+            let penalty = missing_penalty[*attribute_index];
+            let attribute = &data.get_attributes()[*attribute_index];
+            // Dates are compared as minutes since 2000
+            if let Some(data) = attribute.as_numeric() {
+                return data
+                    .iter()
+                    .map(|lhs| match (lhs, rhs) {
+                        (Some(lhs), DataEntry::Numeric(rhs)) => distance_squared(lhs, rhs),
+                        (_, DataEntry::Nominal(_)) => unreachable!(),
+                        _ => penalty,
+                    })
+                    .collect();
+            }
+            match attribute.get_data() {
+                Data::Nominal(nominal) => nominal
+                    .iter()
+                    .map(|lhs| match (lhs, rhs) {
+                        (Some(lhs), DataEntry::Nominal(rhs)) => distance_nominal(lhs, rhs),
+                        (_, DataEntry::Numeric(_)) => unreachable!(),
+                        _ => penalty,
+                    })
+                    .collect(),
+                _ => panic!("Need to implement more types!"),
+            }
+        })
+        .collect();
+
+    (0..data.get_data_len())
+        .map(|data_index| {
+            // Note that there is no need to square-root since it doesn't change ordering
+            (0..distances.len())
+                .map(|attribute_index| distances[attribute_index][data_index])
+                .sum::<f32>()
+        })
+        .collect()
+}
+
 /// Three forward slashes creates a doc-comment
 /// Doc-comments only appear for public parts so this won't appear in the docs
 // Returns distance of 1 if they're different or 0 if they're the same
-fn distance_nominal(lhs: u8, rhs: u8) -> f32 {
+fn distance_nominal(lhs: u32, rhs: u32) -> f32 {
     match lhs == rhs {
         true => 0.0,
         false => 1.0,
@@ -127,3 +231,46 @@ fn distance_nominal(lhs: u8, rhs: u8) -> f32 {
 fn distance_squared(lhs: f32, rhs: f32) -> f32 {
     (lhs - rhs).powi(2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_target() {
+        let data_set: DataSet = "@relation test
+            @attribute size numeric
+            @attribute class {a, b}
+            @data
+            1,?
+            2,?"
+        .parse()
+        .unwrap();
+        assert!(matches!(
+            KNN::new(data_set, "class"),
+            Err(TargetError::NoKnownValues { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_file_round_trip() {
+        let train_set = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let test_set = DataSet::import("./test_data/lakesA2.arff").unwrap();
+        let knn = KNN::new(train_set, "ph").unwrap();
+        let loaded = crate::model_file::tests::round_trip(&knn);
+        (0..test_set.get_data_len()).for_each(|index| {
+            let nearest_neighbors = knn.nearest_neighbors(&test_set, index);
+            assert_eq!(
+                loaded.nearest_neighbors(&test_set, index),
+                nearest_neighbors
+            );
+            [1, 5, 15].iter().for_each(|k| {
+                assert_eq!(
+                    loaded.query_k(&nearest_neighbors, *k),
+                    knn.query_k(&nearest_neighbors, *k)
+                )
+            });
+        });
+    }
+}
@@ -1,10 +1,14 @@
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
 
+pub mod correlation;
 pub mod data_set;
 pub mod id_3;
 pub mod knn;
 pub mod linear;
+#[cfg(feature = "serde")]
+pub mod model_file;
+pub mod multi_instance;
 pub mod naive_bayes;
 pub mod test_statistics;
 
@@ -56,6 +60,49 @@ where
     counts.iter().max_by_key(|a| a.1).unwrap().0.to_owned()
 }
 
+/// Same as majority_vote_ordered but every vote counts for its weight
+#[inline]
+pub fn weighted_majority_vote_ordered<I, T>(iter: I) -> T
+where
+    I: IntoIterator<Item = (T, f32)>,
+    T: Eq + Ord + Copy,
+{
+    let mut counts = BTreeMap::new();
+
+    iter.into_iter().for_each(|(element, weight)| {
+        *counts.entry(element).or_insert(0.0) += weight;
+    });
+
+    // max_by also returns the last max so ties still go to the highest ordered element
+    counts
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .unwrap()
+        .0
+        .to_owned()
+}
+
+/// Same as majority_vote but every vote counts for its weight
+#[inline]
+pub fn weighted_majority_vote<I, T>(iter: I) -> T
+where
+    I: IntoIterator<Item = (T, f32)>,
+    T: Eq + Hash + Copy,
+{
+    let mut counts = HashMap::new();
+
+    iter.into_iter().for_each(|(element, weight)| {
+        *counts.entry(element).or_insert(0.0) += weight;
+    });
+
+    counts
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .unwrap()
+        .0
+        .to_owned()
+}
+
 /// Returns the element with most votes, tie breaks are random
 #[inline]
 pub fn majority_vote<I, T>(iter: I) -> T
@@ -1,6 +1,7 @@
-use std::iter;
+use std::error::Error;
+use std::{fmt, iter};
 
-use crate::data_set::DataSet;
+use crate::data_set::{DataSet, Target, TargetError};
 use itertools::Itertools;
 use rand::{seq::SliceRandom, thread_rng};
 
@@ -11,11 +12,14 @@ const ITERATIONS: usize = 30; // 20 to 40 seems like a good range
 const ALPHA_LOW: f64 = 0.000_000_000_1;
 const ALPHA_MAX: f64 = 1.0;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinearModel {
     data: Vec<Vec<f64>>,
     max_values: Vec<f64>,
     min_values: Vec<f64>,
     weights: Vec<f64>,
+    // Squared errors are scaled by these so heavier instances pull the fit harder
+    instance_weights: Vec<f64>,
     attribute_map: Vec<usize>,
     target_index: usize,
     predictions: Vec<f64>,
@@ -24,10 +28,41 @@ pub struct LinearModel {
 }
 
 impl LinearModel {
-    pub fn new(train_set: &DataSet, target_index: usize) -> Self {
-        let mut data: Vec<_> = (0..(train_set.get_len()))
-            .map(|index| train_set.get_attributes()[index].assume_numeric())
-            .map(|array| array.iter().map(|&x| x as f64).collect::<Vec<_>>())
+    /// Rows with a missing value in any attribute are left out of training.
+    /// Each row's squared error is multiplied by its instance weight.
+    /// target can be an index, a label, "first" or "last" and has to be numeric,
+    /// like every other attribute (see Encoder for nominal attributes)
+    pub fn new(train_set: &DataSet, target: impl Into<Target>) -> Result<Self, LinearError> {
+        let target_index = target.into().resolve_numeric(train_set)?;
+        let columns: Vec<_> = train_set
+            .get_attributes()
+            .iter()
+            .map(|attribute| {
+                attribute
+                    .as_numeric()
+                    .ok_or_else(|| LinearError::NotNumeric {
+                        attribute: attribute.label.clone(),
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        let complete_rows: Vec<_> = (0..train_set.get_data_len())
+            .filter(|&row| columns.iter().all(|column| !column.is_missing(row)))
+            .collect();
+        if complete_rows.is_empty() {
+            return Err(LinearError::NoCompleteRows);
+        }
+        let instance_weights: Vec<_> = complete_rows
+            .iter()
+            .map(|&row| train_set.get_weight(row) as f64)
+            .collect();
+        let mut data: Vec<_> = columns
+            .iter()
+            .map(|column| {
+                complete_rows
+                    .iter()
+                    .map(|&row| column.get(row).unwrap() as f64)
+                    .collect::<Vec<_>>()
+            })
             .collect();
 
         let one_over_n = 1.0 / data[0].len() as f64;
@@ -79,17 +114,18 @@ impl LinearModel {
         let partial_predictions = vec![0.0; data[0].len().min(CHUNK_SIZE)];
         let sum_of_squared_error = f64::MAX;
 
-        Self {
+        Ok(Self {
             data,
             max_values,
             min_values,
             weights,
+            instance_weights,
             attribute_map,
             target_index,
             predictions,
             partial_predictions,
             sum_of_squared_error,
-        }
+        })
     }
 
     pub fn regress(&mut self) {
@@ -125,10 +161,11 @@ impl LinearModel {
                                     self.data[self.target_index][instance],
                                     self.predictions[instance],
                                     self.data[index][instance],
+                                    self.instance_weights[instance],
                                 )
                             })
-                            .map(|(actual, predicted, attribute_value)| {
-                                (actual - predicted) * attribute_value
+                            .map(|(actual, predicted, attribute_value, weight)| {
+                                (actual - predicted) * attribute_value * weight
                             })
                             .sum::<f64>()
                     })
@@ -140,9 +177,10 @@ impl LinearModel {
                                 (
                                     self.data[self.target_index][instance],
                                     self.predictions[instance],
+                                    self.instance_weights[instance],
                                 )
                             })
-                            .map(|(actual, predicted)| (actual - predicted))
+                            .map(|(actual, predicted, weight)| (actual - predicted) * weight)
                             .sum::<f64>(),
                     ))
                     .collect();
@@ -195,28 +233,37 @@ impl LinearModel {
         }
 
         // Did some tricky algebra to convert back to un-normalized weights
-        let converted_weights: Vec<_> = (0..self.weights.len() - 1)
-            .map(|index| {
-                ((self.max_values.last().unwrap() - self.min_values.last().unwrap())
-                    / (self.max_values[index] - self.min_values[index]))
+        // Weight i belongs to attribute attribute_map[i], the target can be any column
+        let (target_min, target_max) = (
+            self.min_values[self.target_index],
+            self.max_values[self.target_index],
+        );
+        let converted_weights: Vec<_> = self
+            .attribute_map
+            .iter()
+            .enumerate()
+            .map(|(index, &attribute)| {
+                ((target_max - target_min)
+                    / (self.max_values[attribute] - self.min_values[attribute]))
                     * self.weights[index]
             })
             .chain(iter::once(
-                (0..self.weights.len() - 1)
-                    .map(|index| {
-                        ((self.min_values[index] * self.weights[index])
-                            * (self.min_values.last().unwrap() - self.max_values.last().unwrap()))
-                            / (self.max_values[index] - self.min_values[index])
+                self.attribute_map
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &attribute)| {
+                        ((self.min_values[attribute] * self.weights[index])
+                            * (target_min - target_max))
+                            / (self.max_values[attribute] - self.min_values[attribute])
                     })
                     .sum::<f64>()
-                    + self.min_values.last().unwrap()
-                    + (self.weights.last().unwrap()
-                        * (self.max_values.last().unwrap() - self.min_values.last().unwrap())),
+                    + target_min
+                    + (self.weights.last().unwrap() * (target_max - target_min)),
             ))
             .collect();
 
-        let converted_sum_of_squared_error = self.sum_of_squared_error
-            * (self.max_values.last().unwrap() - self.min_values.last().unwrap()).powi(2);
+        let converted_sum_of_squared_error =
+            self.sum_of_squared_error * (target_max - target_min).powi(2);
 
         println!("Iterations: {}", iterations);
         println!("Raw weights: {:.5?}", self.weights);
@@ -242,9 +289,10 @@ impl LinearModel {
                 (
                     self.data[self.target_index][instance],
                     self.predictions[instance],
+                    self.instance_weights[instance],
                 )
             })
-            .map(|(actual, predicted)| (actual - predicted).powi(2))
+            .map(|(actual, predicted, weight)| (actual - predicted).powi(2) * weight)
             .sum::<f64>()
     }
 
@@ -266,9 +314,140 @@ impl LinearModel {
                 (
                     self.data[self.target_index][random_indices[index]],
                     self.partial_predictions[index],
+                    self.instance_weights[random_indices[index]],
                 )
             })
-            .map(|(actual, predicted)| (actual - predicted).powi(2))
+            .map(|(actual, predicted, weight)| (actual - predicted).powi(2) * weight)
             .sum::<f64>()
     }
 }
+
+// Checks for models read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl LinearModel {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let columns = self.data.len();
+        let rows = self.data.first().map_or(0, Vec::len);
+        let shapes_match = rows > 0
+            && self.data.iter().all(|column| column.len() == rows)
+            && self.min_values.len() == columns
+            && self.max_values.len() == columns
+            && self.target_index < columns
+            && self
+                .attribute_map
+                .iter()
+                .all(|attribute| *attribute < columns)
+            && self.weights.len() == self.attribute_map.len() + 1
+            && self.instance_weights.len() == rows
+            && self.predictions.len() == rows
+            && self.partial_predictions.len() == rows.min(CHUNK_SIZE);
+        match shapes_match {
+            true => Ok(()),
+            false => Err("LinearModel's weights don't match its rows".to_owned()),
+        }
+    }
+}
+
+/// Why a LinearModel couldn't be trained
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinearError {
+    Target(TargetError),
+    /// Every row is missing at least one value so there's nothing to train on
+    NoCompleteRows,
+    /// An attribute that isn't numeric (or a date)
+    NotNumeric {
+        attribute: String,
+    },
+}
+
+impl From<TargetError> for LinearError {
+    fn from(error: TargetError) -> Self {
+        Self::Target(error)
+    }
+}
+
+impl fmt::Display for LinearError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Target(error) => write!(f, "{}", error),
+            Self::NoCompleteRows => write!(f, "Every row is missing at least one value"),
+            Self::NotNumeric { attribute } => write!(
+                f,
+                "Linear regression needs numeric attributes but '{}' isn't numeric",
+                attribute
+            ),
+        }
+    }
+}
+
+impl Error for LinearError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Target(error) => Some(error),
+            Self::NoCompleteRows | Self::NotNumeric { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_complete_rows() {
+        let data_set: DataSet = "@relation test
+            @attribute x numeric
+            @attribute y numeric
+            @data
+            1,?
+            ?,2"
+        .parse()
+        .unwrap();
+        assert_eq!(
+            LinearModel::new(&data_set, "y").err(),
+            Some(LinearError::NoCompleteRows)
+        );
+        assert!(matches!(
+            LinearModel::new(&data_set, "z"),
+            Err(LinearError::Target(TargetError::NotFound { .. }))
+        ));
+
+        let unknown_target: DataSet = "@relation test
+            @attribute x numeric
+            @attribute y numeric
+            @data
+            1,?
+            2,?"
+        .parse()
+        .unwrap();
+        assert_eq!(
+            LinearModel::new(&unknown_target, "y").err(),
+            Some(LinearError::NoCompleteRows)
+        );
+
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        assert_eq!(
+            LinearModel::new(&lakes, "area").err(),
+            Some(LinearError::NotNumeric {
+                attribute: "inlets".to_owned()
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_file_round_trip() {
+        let data_set = DataSet::import("./test_data/linear_test.arff").unwrap();
+        let mut linear_model = LinearModel::new(&data_set, "W").unwrap();
+        // Weights that aren't all the same so every one of them has to survive
+        linear_model.weights = (0..linear_model.weights.len())
+            .map(|index| index as f64 * 0.3 - 0.5)
+            .collect();
+        let mut loaded = crate::model_file::tests::round_trip(&linear_model);
+        assert_eq!(
+            loaded.sum_of_squared_error(),
+            linear_model.sum_of_squared_error()
+        );
+        assert_eq!(loaded.predictions, linear_model.predictions);
+    }
+}
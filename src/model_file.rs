@@ -0,0 +1,413 @@
+//! Binary file format for trained models and data sets, only built with the serde feature.
+//!
+//! A file starts with a 6 byte header: the magic bytes ARFM, the format version
+//! and the kind of model that's stored, followed by the model itself written with bincode.
+//! Loading a file checks the header before reading the model, data sets (including the ones
+//! inside models) are checked as they're read and every model is checked once it's read,
+//! so a corrupt file is a ModelError rather than a panic when the model is used
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use bincode::{ErrorKind, Options};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_set::{
+        DataSet, Discretizer, Encoder, Imputer, MinMaxScaler, RobustScaler, StandardScaler,
+    },
+    id_3::{ProcessedData, ID3},
+    knn::KNN,
+    linear::LinearModel,
+    naive_bayes::NaiveBayes,
+};
+
+pub const MAGIC: [u8; 4] = *b"ARFM";
+/// Bumped whenever the layout of a model changes. Files from any other version are refused
+/// since there's no way to convert between layouts, so models have to be trained again
+pub const VERSION: u8 = 2;
+
+/// What a model file holds, written as one byte after the version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    DataSet,
+    ProcessedData,
+    ID3,
+    NaiveBayes,
+    KNN,
+    LinearModel,
+    Discretizer,
+    Encoder,
+    Imputer,
+    MinMaxScaler,
+    StandardScaler,
+    RobustScaler,
+}
+
+impl ModelKind {
+    // In the order of their bytes, so new kinds go at the end
+    const ALL: [Self; 12] = [
+        Self::DataSet,
+        Self::ProcessedData,
+        Self::ID3,
+        Self::NaiveBayes,
+        Self::KNN,
+        Self::LinearModel,
+        Self::Discretizer,
+        Self::Encoder,
+        Self::Imputer,
+        Self::MinMaxScaler,
+        Self::StandardScaler,
+        Self::RobustScaler,
+    ];
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.get(byte as usize).copied()
+    }
+
+    fn to_byte(self) -> u8 {
+        // Every kind is in ALL
+        Self::ALL.iter().position(|kind| *kind == self).unwrap() as u8
+    }
+}
+
+/// Anything that can be saved to and loaded from a model file.
+/// KIND is written to the file so a file can't be loaded as the wrong kind of model
+pub trait Model: Serialize + for<'de> Deserialize<'de> {
+    const KIND: ModelKind;
+
+    /// Checks a model that was just read fits together, the reason is returned when it doesn't
+    fn check_loaded(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_to(&self, mut writer: impl Write) -> Result<(), ModelError> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION, Self::KIND.to_byte()])?;
+        options().serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn read_from(mut reader: impl Read) -> Result<Self, ModelError> {
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(ModelError::NotAModel);
+        }
+        let (version, kind) = (header[4], header[5]);
+        if version != VERSION {
+            return Err(ModelError::Version { found: version });
+        }
+        let kind = ModelKind::from_byte(kind)
+            .ok_or_else(|| ModelError::Format(format!("Unknown kind of model {}", kind)))?;
+        if kind != Self::KIND {
+            return Err(ModelError::Kind {
+                expected: Self::KIND,
+                found: kind,
+            });
+        }
+        let model: Self = options().deserialize_from(reader)?;
+        model.check_loaded().map_err(ModelError::Format)?;
+        Ok(model)
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> Result<(), ModelError> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+// Varint integers and little endian floats, reading and writing have to agree
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+impl Model for DataSet {
+    const KIND: ModelKind = ModelKind::DataSet;
+}
+
+impl Model for Discretizer {
+    const KIND: ModelKind = ModelKind::Discretizer;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for Encoder {
+    const KIND: ModelKind = ModelKind::Encoder;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for Imputer {
+    const KIND: ModelKind = ModelKind::Imputer;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for MinMaxScaler {
+    const KIND: ModelKind = ModelKind::MinMaxScaler;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for StandardScaler {
+    const KIND: ModelKind = ModelKind::StandardScaler;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for RobustScaler {
+    const KIND: ModelKind = ModelKind::RobustScaler;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+// ID3 needs the ProcessedData it was trained on (or just its layout) to import test sets
+impl Model for ProcessedData {
+    const KIND: ModelKind = ModelKind::ProcessedData;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for ID3 {
+    const KIND: ModelKind = ModelKind::ID3;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for NaiveBayes {
+    const KIND: ModelKind = ModelKind::NaiveBayes;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for KNN {
+    const KIND: ModelKind = ModelKind::KNN;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+impl Model for LinearModel {
+    const KIND: ModelKind = ModelKind::LinearModel;
+
+    fn check_loaded(&self) -> Result<(), String> {
+        self.check()
+    }
+}
+
+/// Why a model couldn't be saved or loaded
+#[derive(Debug)]
+pub enum ModelError {
+    Io(io::Error),
+    /// The file doesn't start with the magic bytes
+    NotAModel,
+    /// The file was written by another version of the format
+    Version {
+        found: u8,
+    },
+    /// The file holds another kind of model
+    Kind {
+        expected: ModelKind,
+        found: ModelKind,
+    },
+    /// The model itself couldn't be written or read, e.g. the file is corrupt
+    Format(String),
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::NotAModel => write!(f, "Not a model file"),
+            Self::Version { found } => write!(
+                f,
+                "Model file is version {} but only version {} is supported",
+                found, VERSION
+            ),
+            Self::Kind { expected, found } => {
+                write!(
+                    f,
+                    "Expected a {:?} model but found a {:?} model",
+                    expected, found
+                )
+            }
+            Self::Format(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ModelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ModelError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<bincode::Error> for ModelError {
+    fn from(error: bincode::Error) -> Self {
+        match *error {
+            ErrorKind::Io(error) => Self::Io(error),
+            error => Self::Format(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::data_set::{Binning, Scaler};
+
+    // Writes model to memory and reads it back
+    pub(crate) fn round_trip<M: Model>(model: &M) -> M {
+        let mut bytes = Vec::new();
+        model.write_to(&mut bytes).unwrap();
+        M::read_from(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data_set: DataSet = "@relation 'round trip'
+            @attribute size numeric
+            @attribute colour {red, blue}
+            @attribute name string
+            @attribute when date 'yyyy-MM-dd HH:mm'
+            @attribute bag relational
+                @attribute x numeric
+            @end bag
+            @data
+            1.5,red,'a b','2023-01-02 03:04',\"0\\n1\"
+            -2,?,c,?,?,{2}
+            ?,blue,d,'1969-12-31 23:59',\"\""
+            .parse()
+            .unwrap();
+        assert_eq!(round_trip(&data_set), data_set);
+
+        // Every permutation of the attributes so the tree has to learn all of them
+        let train = DataSet::import("./test_data/test.arff").unwrap();
+        let processed = ProcessedData::import(&train);
+        let mut id3 = ID3::new();
+        id3.train(&processed, "last").unwrap();
+        let mut bytes = Vec::new();
+        id3.write_to(&mut bytes).unwrap();
+        let loaded = ID3::read_from(bytes.as_slice()).unwrap();
+        for row in 0..train.get_data_len() {
+            let row: Vec<_> = (0..train.get_len())
+                .map(|column| train.get_value(column, row).assume_nominal())
+                .collect();
+            assert_eq!(loaded.query(&row), id3.query(&row));
+        }
+
+        assert_eq!(bytes[..6], *b"ARFM\x02\x02");
+        assert!(matches!(
+            NaiveBayes::read_from(bytes.as_slice()),
+            Err(ModelError::Kind {
+                expected: ModelKind::NaiveBayes,
+                found: ModelKind::ID3,
+            })
+        ));
+        assert!(matches!(
+            ID3::read_from(&bytes[..bytes.len() - 1]),
+            Err(ModelError::Io(_))
+        ));
+        assert!(matches!(
+            ID3::read_from(&b"ARFF\x02\x02"[..]),
+            Err(ModelError::NotAModel)
+        ));
+        // Older layouts are refused as well as newer ones
+        for version in [1, 3] {
+            bytes[4] = version;
+            assert!(matches!(
+                ID3::read_from(bytes.as_slice()),
+                Err(ModelError::Version { found }) if found == version
+            ));
+        }
+    }
+
+    #[test]
+    fn test_corrupt_model() {
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let knn = KNN::new(lakes, "ph").unwrap();
+        let mut bytes = Vec::new();
+        knn.write_to(&mut bytes).unwrap();
+        assert!(KNN::read_from(bytes.as_slice()).is_ok());
+
+        // The target is the last thing written, a single byte since it's small
+        let target = bytes.len() - 1;
+        assert_eq!(bytes[target], 18);
+        for corrupt in [0, 19, 200] {
+            bytes[target] = corrupt;
+            assert!(matches!(
+                KNN::read_from(bytes.as_slice()),
+                Err(ModelError::Format(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_filters() {
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+
+        let discretizer = Discretizer::unsupervised(&lakes, Binning::EqualFrequency(4));
+        let loaded = round_trip(&discretizer);
+        assert_eq!(loaded, discretizer);
+        assert_eq!(loaded.apply(&lakes), discretizer.apply(&lakes));
+
+        let encoder = Encoder::one_hot(&lakes, "ph", true).unwrap();
+        let loaded = round_trip(&encoder);
+        assert_eq!(loaded, encoder);
+        assert_eq!(loaded.transform(&lakes), encoder.transform(&lakes));
+
+        let imputer = Imputer::median(&lakes);
+        let loaded = round_trip(&imputer);
+        assert_eq!(loaded, imputer);
+        assert_eq!(loaded.transform(&lakes), imputer.transform(&lakes));
+        let by_class = Imputer::class_conditional(&lakes, "ph").unwrap();
+        assert_eq!(round_trip(&by_class), by_class);
+        let nearest = Imputer::nearest_neighbors(&lakes, 3).unwrap();
+        assert_eq!(round_trip(&nearest), nearest);
+
+        let min_max = MinMaxScaler::fit(&lakes);
+        assert_eq!(round_trip(&min_max), min_max);
+        let standard = StandardScaler::fit(&lakes);
+        assert_eq!(round_trip(&standard), standard);
+        let robust = RobustScaler::fit(&lakes);
+        let loaded = round_trip(&robust);
+        assert_eq!(loaded, robust);
+        assert_eq!(loaded.transform(&lakes), robust.transform(&lakes));
+    }
+}
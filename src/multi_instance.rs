@@ -0,0 +1,167 @@
+use crate::data_set::{Attribute, Data, DataSet, Numeric, Relational};
+
+/// How a bag of instances gets boiled down to a single row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Summary {
+    /// Mean of every numeric attribute in the bag
+    Mean,
+    /// Smallest and largest value of every numeric attribute in the bag
+    MinMax,
+}
+
+/// Turns multi-instance data into regular data so the existing learners can train on it.
+/// Every relational attribute is replaced by bag level attributes:
+/// numeric ones (dates included) become their mean or min and max over the bag
+/// and nominal ones become the fraction of the bag's instances with each value.
+/// Missing or empty bags give missing summaries, other attributes are copied over as they are.
+/// Every row keeps its instance weight
+pub fn summarize(data_set: &DataSet, summary: Summary) -> DataSet {
+    let attributes = data_set
+        .get_attributes()
+        .iter()
+        .flat_map(|attribute| match attribute.get_data() {
+            Data::Relational(bags) => summarize_bags(&attribute.label, bags, summary),
+            _ => vec![attribute.clone()],
+        })
+        .collect();
+    let mut summarized = DataSet::new(data_set.get_relation(), attributes);
+    // Rows stay in the same order, there are just none left if every attribute was dropped
+    if data_set.is_weighted() && summarized.get_len() > 0 {
+        summarized.set_weights(
+            (0..data_set.get_data_len())
+                .map(|row| data_set.get_weight(row))
+                .collect(),
+        );
+    }
+    summarized
+}
+
+// Strings and nested relational attributes don't have a summary so they're dropped
+fn summarize_bags(label: &str, bags: &Relational, summary: Summary) -> Vec<Attribute> {
+    bags.get_header()
+        .get_attributes()
+        .iter()
+        .enumerate()
+        .flat_map(|(column, nested)| {
+            let name = format!("{}_{}", label, nested.label);
+            let values = |bag: &DataSet| {
+                bag.get_attributes()[column]
+                    .as_numeric()
+                    .map(|data| data.iter().flatten().collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            match nested.get_data() {
+                Data::Nominal(nominal) => (0..nominal.size() as u32)
+                    .map(|id| {
+                        let label = format!("{}={}", name, nominal.get_fields()[id as usize]);
+                        numeric_attribute(label, bags.iter().map(|bag| frequency(bag?, column, id)))
+                    })
+                    .collect(),
+                _ if nested.as_numeric().is_some() => {
+                    let stats: Vec<_> = bags.iter().map(|bag| stats(&values(bag?))).collect();
+                    match summary {
+                        Summary::Mean => vec![numeric_attribute(
+                            format!("{}_mean", name),
+                            stats.iter().map(|stats| stats.map(|(mean, _, _)| mean)),
+                        )],
+                        Summary::MinMax => vec![
+                            numeric_attribute(
+                                format!("{}_min", name),
+                                stats.iter().map(|stats| stats.map(|(_, min, _)| min)),
+                            ),
+                            numeric_attribute(
+                                format!("{}_max", name),
+                                stats.iter().map(|stats| stats.map(|(_, _, max)| max)),
+                            ),
+                        ],
+                    }
+                }
+                _ => vec![],
+            }
+        })
+        .collect()
+}
+
+// (mean, min, max) of the known values, None if there aren't any
+fn stats(values: &[f32]) -> Option<(f32, f32, f32)> {
+    if values.is_empty() {
+        return None;
+    }
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    Some((mean, min, max))
+}
+
+// Fraction of the bag's known values in column that are id
+fn frequency(bag: &DataSet, column: usize, id: u32) -> Option<f32> {
+    let known: Vec<_> = bag.get_attributes()[column]
+        .assume_nominal()
+        .iter()
+        .flatten()
+        .collect();
+    let matching = known.iter().filter(|value| **value == id).count();
+    (!known.is_empty()).then(|| matching as f32 / known.len() as f32)
+}
+
+fn numeric_attribute(label: String, values: impl Iterator<Item = Option<f32>>) -> Attribute {
+    let mut data = Numeric::new();
+    values.for_each(|value| match value {
+        Some(value) => data.push(value),
+        None => data.push_missing(),
+    });
+    Attribute {
+        label,
+        data: Data::Numeric(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_set::DataEntry;
+
+    #[test]
+    fn test_summarize() {
+        let data_set: DataSet = "@relation musk
+            @attribute bag relational
+              @attribute f1 numeric
+              @attribute shape {round, flat}
+            @end bag
+            @attribute class {0, 1}
+            @data
+            \"1,round\\n3,flat\\n?,flat\\n8,flat\",1
+            ?,0,{2.5}"
+            .parse()
+            .unwrap();
+
+        let summary = summarize(&data_set, Summary::MinMax);
+        let labels: Vec<_> = summary
+            .get_attributes()
+            .iter()
+            .map(|attribute| attribute.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "bag_f1_min",
+                "bag_f1_max",
+                "bag_shape=round",
+                "bag_shape=flat",
+                "class"
+            ]
+        );
+        assert_eq!(summary.get_value(0, 0), DataEntry::Numeric(1.0));
+        assert_eq!(summary.get_value(1, 0), DataEntry::Numeric(8.0));
+        assert_eq!(summary.get_value(3, 0), DataEntry::Numeric(0.75));
+        assert_eq!(summary.get_value(0, 1), DataEntry::Missing);
+        assert_eq!(summary.get_value(4, 1), DataEntry::Nominal(0));
+        assert_eq!(summary.get_weight(0), 1.0);
+        assert_eq!(summary.get_weight(1), 2.5);
+
+        let summary = summarize(&data_set, Summary::Mean);
+        assert_eq!(summary.get_value(0, 0), DataEntry::Numeric(4.0));
+        assert_eq!(summary.get_len(), 4);
+    }
+}
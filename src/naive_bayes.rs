@@ -1,23 +1,42 @@
-use crate::{data_set::DataSet, test_statistics::ConfusionMatrix};
+use crate::{
+    data_set::{DataEntry, DataSet, Discretizer, HeaderError, Target, TargetError},
+    test_statistics::ConfusionMatrix,
+};
 
 /// Basic implementation of the Naive Bayes algorithm
 /// with smoothing
+///
+/// Missing values are skipped: they aren't counted while training and
+/// don't contribute a factor to the probability when querying.
+/// Instances count for their weight, so a weight of 2 is the same as two copies of the instance
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NaiveBayes {
     // array[target_value] = P(target_value)
     probability_target: Vec<f32>,
     // array[target_value][attribute_index][attribute_value] = P(target_value | attribute_value)
     probability_given: Vec<Vec<Vec<f32>>>,
+    // Column of the target
+    target: usize,
+    // Training attributes without any rows, test sets have to match
+    header: DataSet,
+    // Set by new_from_numeric, test sets get discretized the same way as the training set
+    discretizer: Option<Discretizer>,
 }
 
 impl NaiveBayes {
     /// Creates model trained on data
     /// Assumes all data is nominal, will panic if any attribute is numeric
-    /// Use new_from_numeric(...) to handle importing numeric data
+    /// Use new_from_numeric(...) to handle numeric data
     /// k is the smoothing factor
-    pub fn new(data: &DataSet, target: usize, k: usize) -> Self {
+    /// target can be an index, a label, "first" or "last" and has to be nominal,
+    /// at least one entry needs a target value
+    pub fn new(data: &DataSet, target: impl Into<Target>, k: usize) -> Result<Self, TargetError> {
+        let target = target.into().resolve_nominal(data)?;
         // Target value -> [indices with target value]
         let target_feature_size = data.get_attributes()[target].assume_nominal().size();
+        // Entries with a missing target value are left out
         let target_indices = (0..data.get_data_len())
+            .filter(|index| !data.get_value(target, *index).is_missing())
             .map(|index| (index, data.get_value(target, index).assume_nominal()))
             .fold(
                 vec![vec![]; target_feature_size],
@@ -31,9 +50,22 @@ impl NaiveBayes {
         // You need to tell Rust the type of collection to collect into
         // However it's pretty simple to infer the type inside the collection
         // An alternative to Vec<_> is .collect::<Vec<_>>()
+        let weight_of = |indices: &Vec<usize>| {
+            indices
+                .iter()
+                .map(|index| data.get_weight(*index))
+                .sum::<f32>()
+        };
+        let known_targets = target_indices.iter().map(weight_of).sum::<f32>();
+        // Every prior would be 0 / 0
+        if known_targets == 0.0 {
+            return Err(TargetError::NoKnownValues {
+                attribute: data.get_attributes()[target].label.clone(),
+            });
+        }
         let probability_target: Vec<_> = target_indices
             .iter()
-            .map(|indices| indices.len() as f32 / data.get_data_len() as f32)
+            .map(|indices| weight_of(indices) / known_targets)
             .collect(); // .collect::<Vec<_>>();
 
         // array[target_value][attribute_index][attribute_value] = P(target_value | attribute_value)
@@ -54,18 +86,28 @@ impl NaiveBayes {
                         // For each entry that has given target value
                         let attribute_value_counts = target_value_indices
                             .iter()
-                            .map(|index| data.get_value(attribute_index, *index).assume_nominal())
-                            .fold(vec![0; attribute_values], |mut counts, attribute_value| {
-                                // Count the number each feature occurs for each attribute
-                                counts[attribute_value as usize] += 1;
-                                counts
-                            });
+                            .map(|index| {
+                                (
+                                    data.get_value(attribute_index, *index),
+                                    data.get_weight(*index),
+                                )
+                            })
+                            .filter(|(attribute_value, _)| !attribute_value.is_missing())
+                            .fold(
+                                vec![0.0; attribute_values],
+                                |mut counts, (attribute_value, weight)| {
+                                    // Count the number each feature occurs for each attribute
+                                    counts[attribute_value.assume_nominal() as usize] += weight;
+                                    counts
+                                },
+                            );
+                        // Only the entries where the attribute is known
+                        let known = attribute_value_counts.iter().sum::<f32>();
                         // Calculate the probability of target given feature
                         attribute_value_counts
                             .iter()
                             .map(|count| {
-                                (count + k) as f32
-                                    / (target_value_indices.len() + (attribute_values * k)) as f32
+                                (count + k as f32) / (known + (attribute_values * k) as f32)
                             })
                             .collect()
                     })
@@ -75,30 +117,50 @@ impl NaiveBayes {
             // Collect all the probabilities together
             .collect();
 
-        Self {
+        Ok(Self {
             probability_target,
             probability_given,
-        }
+            target,
+            header: data.header(),
+            discretizer: None,
+        })
     }
 
-    /// Not yet implemented!
-    /// Will handle conversion from numeric to nominal data then train model on that data
-    pub fn new_from_numeric(data: DataSet, target: usize) -> Self {
-        todo!("{:?} {}", data, target)
+    /// Same as new but numeric (and date) attributes are discretized first with
+    /// Discretizer::supervised, like Weka's NaiveBayes with -D.
+    /// Test sets keep their numeric attributes, they're discretized with the same cut points
+    pub fn new_from_numeric(
+        data: &DataSet,
+        target: impl Into<Target>,
+        k: usize,
+    ) -> Result<Self, TargetError> {
+        let target = target.into();
+        let discretizer = Discretizer::supervised(data, target.clone())?;
+        // The discretizer was just fit on data so the header matches
+        let discretized = discretizer.apply(data).unwrap();
+        Ok(Self {
+            header: data.header(),
+            discretizer: Some(discretizer),
+            ..Self::new(&discretized, target, k)?
+        })
     }
 
     /// Query an entry. Assumes entry matches a data entry of the training set
     /// with target value included. Meant for to be used for testing
-    fn query(&self, entry: &[u8], target: usize) -> u8 {
+    fn query(&self, entry: &[Option<u32>]) -> u32 {
+        let target = self.target;
         // For each target value
         (0..self.probability_given.len())
             .map(|target_value| {
                 let probability = (0..entry.len())
                     .filter(|&index| index != target)
                     // Dirty fix for out of bounds index
-                    .map(|attribute_index| {
-                        self.probability_given[target_value][attribute_index]
-                            [entry[attribute_index] as usize]
+                    // Missing attributes are skipped by filter_map
+                    .filter_map(|attribute_index| {
+                        entry[attribute_index].map(|attribute_value| {
+                            self.probability_given[target_value][attribute_index]
+                                [attribute_value as usize]
+                        })
                     })
                     // Rust struggles with inferring type for product() and sum()
                     .product::<f32>()
@@ -108,29 +170,43 @@ impl NaiveBayes {
             // In this context _ is used to tell the compiler you don't need that variable
             // Here we just need to compare the probabilities
             .max_by(|(_, prob_a), (_, prob_b)| prob_a.partial_cmp(prob_b).unwrap())
-            .map(|(target_value, _)| target_value as u8)
+            .map(|(target_value, _)| target_value as u32)
             .unwrap()
     }
 
     /// Test a set of data with known target values to calculate the accuracy
-    /// target is the target index of the test_set
+    /// The test_set needs the same attributes as the training data (see DataSet::align_to), the target is the one trained on
     /// target value is what is considered a positive value for calculations of false positives
-    pub fn test(&self, test_set: &DataSet, target: usize, _target_value: u8) {
+    /// Entries with a missing target value are skipped
+    pub fn test(&self, test_set: &DataSet, _target_value: u32) -> Result<(), HeaderError> {
+        self.header.check_header(test_set)?;
+        let discretized;
+        let test_set = match &self.discretizer {
+            Some(discretizer) => {
+                discretized = discretizer.apply(test_set)?;
+                &discretized
+            }
+            None => test_set,
+        };
+        let target = self.target;
         // Now  works for any finite set of target features
-        let target_feature_size = test_set.get_attributes()[target].assume_nominal().size();
-        let count = (0..test_set.get_data_len())
-            // data index -> entry
-            .map(|data_index| {
-                (0..test_set.get_len())
-                    .map(|attribute_index| {
-                        test_set
-                            .get_value(attribute_index, data_index)
-                            .assume_nominal()
+        let target_feature = test_set.get_attributes()[target].assume_nominal();
+        let target_feature_size = target_feature.size();
+        let count = test_set
+            .rows()
+            .filter(|row| !row.get(target).is_missing())
+            // row -> entry
+            .map(|row| {
+                (0..row.len())
+                    .map(|attribute_index| match row.get_entry(attribute_index) {
+                        DataEntry::Missing => None,
+                        value => Some(value.assume_nominal()),
                     })
                     .collect::<Vec<_>>()
             })
             // entry -> (predicted, actual)
-            .map(|entry| (self.query(&entry, target) as usize, entry[target] as usize))
+            // Target can't be None since those entries were filtered out
+            .map(|entry| (self.query(&entry) as usize, entry[target].unwrap() as usize))
             // fold is an iterator consumer that produces a single value, in this case a Confusion Matrix
             // sum, product, join (from itertools), for_each, collect, etc. are other consumers
             // Iterators are lazy and won't do anything unless they're being "consumed"
@@ -145,5 +221,145 @@ impl NaiveBayes {
 
         // Display the statistics deduced from the matrix
         count.display(1);
+        Ok(())
+    }
+}
+
+// Checks for models read from a model file, the reason is returned when something is wrong
+#[cfg(feature = "serde")]
+impl NaiveBayes {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        use crate::data_set::Data;
+
+        // The tables are laid out like the data the model was trained on, discretized or not
+        let discretized;
+        let layout = match &self.discretizer {
+            Some(discretizer) => {
+                discretizer.check()?;
+                discretized = discretizer.apply(&self.header).map_err(|error| {
+                    format!(
+                        "NaiveBayes's discretizer doesn't match its attributes: {}",
+                        error
+                    )
+                })?;
+                &discretized
+            }
+            None => &self.header,
+        };
+        let sizes: Vec<_> = layout
+            .get_attributes()
+            .iter()
+            .map(|attribute| match attribute.get_data() {
+                Data::Nominal(nominal) => Some(nominal.size()),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or("NaiveBayes has an attribute that isn't nominal")?;
+        let classes = *sizes
+            .get(self.target)
+            .ok_or("NaiveBayes's target is out of range")?;
+        let tables_match = self.probability_target.len() == classes
+            && self.probability_given.len() == classes
+            && self.probability_given.iter().all(|given| {
+                given.len() == sizes.len()
+                    && given
+                        .iter()
+                        .zip(&sizes)
+                        .all(|(probabilities, size)| probabilities.len() == *size)
+            });
+        // NaN probabilities can't be compared when querying
+        let numbers = self
+            .probability_target
+            .iter()
+            .chain(self.probability_given.iter().flatten().flatten())
+            .all(|probability| !probability.is_nan());
+        match tables_match && numbers {
+            true => Ok(()),
+            false => Err("NaiveBayes's probabilities don't match its attributes".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_from_numeric() {
+        let train_set = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let test_set = DataSet::import("./test_data/lakesA2.arff").unwrap();
+        let naive_bayes = NaiveBayes::new_from_numeric(&train_set, "ph", 1).unwrap();
+
+        let discretizer = Discretizer::supervised(&train_set, "ph").unwrap();
+        let discretized =
+            NaiveBayes::new(&discretizer.apply(&train_set).unwrap(), "ph", 1).unwrap();
+        assert_eq!(naive_bayes.probability_given, discretized.probability_given);
+
+        // Test sets are given as they are, not discretized
+        assert!(naive_bayes.test(&test_set, 1).is_ok());
+        assert!(naive_bayes
+            .test(&discretizer.apply(&test_set).unwrap(), 1)
+            .is_err());
+        assert!(matches!(
+            NaiveBayes::new_from_numeric(&train_set, "area", 1),
+            Err(TargetError::WrongType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_target() {
+        let data_set: DataSet = "@relation test
+            @attribute colour {red, blue}
+            @attribute size numeric
+            @attribute class {a, b}
+            @data
+            red,1,?
+            blue,2,?"
+            .parse()
+            .unwrap();
+        let no_known_values = Some(TargetError::NoKnownValues {
+            attribute: "class".to_owned(),
+        });
+        let nominal = data_set.select_attributes(["colour", "class"]).unwrap();
+        assert_eq!(NaiveBayes::new(&nominal, "class", 1).err(), no_known_values);
+        assert_eq!(
+            NaiveBayes::new_from_numeric(&data_set, "class", 1).err(),
+            no_known_values
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_file_round_trip() {
+        let train_set = DataSet::import("./test_data/lakesDiscreteFold1.arff").unwrap();
+        let test_set = DataSet::import("./test_data/lakesDiscreteFold2.arff").unwrap();
+        let naive_bayes = NaiveBayes::new(&train_set, "latitude", 1).unwrap();
+        let loaded = crate::model_file::tests::round_trip(&naive_bayes);
+        test_set.rows().for_each(|row| {
+            let entry: Vec<_> = (0..row.len())
+                .map(|column| match row.get_entry(column) {
+                    DataEntry::Missing => None,
+                    value => Some(value.assume_nominal()),
+                })
+                .collect();
+            assert_eq!(loaded.query(&entry), naive_bayes.query(&entry));
+        });
+
+        // A table short of one value would index out of bounds when querying
+        use crate::model_file::{Model, ModelError};
+        let mut corrupt = naive_bayes;
+        corrupt.probability_given[1][0].pop();
+        let mut bytes = Vec::new();
+        corrupt.write_to(&mut bytes).unwrap();
+        assert!(matches!(
+            NaiveBayes::read_from(bytes.as_slice()),
+            Err(ModelError::Format(_))
+        ));
+
+        // Tables of a discretized model are checked against the discretized attributes
+        let lakes = DataSet::import("./test_data/lakesA1.arff").unwrap();
+        let numeric = NaiveBayes::new_from_numeric(&lakes, "ph", 1).unwrap();
+        let loaded = crate::model_file::tests::round_trip(&numeric);
+        assert_eq!(loaded.probability_given, numeric.probability_given);
     }
 }
@@ -39,7 +39,7 @@ pub struct ConfusionMatrix {
 
 impl ConfusionMatrix {
     pub fn new(n: usize) -> Self {
-        let matrix = Box::from_iter(std::iter::repeat(0).take(n * n));
+        let matrix = Box::from_iter(std::iter::repeat_n(0, n * n));
         ConfusionMatrix { matrix, n }
     }
 